@@ -0,0 +1,300 @@
+use crate::{Node, Tree};
+
+impl Tree {
+    /// Returns the depth of the node `node_id`, the root being at depth 0. Runs in O(height).
+    pub fn depth(&self, node_id: usize) -> usize {
+        assert!(node_id < self.nodes.len(), "Node id is out of range");
+
+        let mut depth = 0;
+        let mut id = node_id;
+        while let Some(parent_id) = self.nodes[id].id_parent {
+            depth += 1;
+            id = parent_id;
+        }
+        depth
+    }
+
+    /// Returns the lowest common ancestor of the nodes `u` and `v`. Runs in O(height).
+    pub fn lca(&self, u: usize, v: usize) -> usize {
+        let (mut u, mut v) = (u, v);
+        let (mut depth_u, mut depth_v) = (self.depth(u), self.depth(v));
+
+        // bring both nodes to the same depth, then climb together until they meet
+        while depth_u > depth_v {
+            u = self.nodes[u].id_parent.unwrap();
+            depth_u -= 1;
+        }
+        while depth_v > depth_u {
+            v = self.nodes[v].id_parent.unwrap();
+            depth_v -= 1;
+        }
+        while u != v {
+            u = self.nodes[u].id_parent.unwrap();
+            v = self.nodes[v].id_parent.unwrap();
+        }
+
+        u
+    }
+
+    /// Precomputes an `LcaIndex` of the tree in O(n log n), to answer LCA queries in O(1).
+    pub fn lca_index(&self) -> LcaIndex {
+        let n = self.nodes.len();
+
+        // parents have smaller ids than their children, so depths can be filled in id order
+        let mut depth = vec![0; n];
+        for id in 1..n {
+            depth[id] = depth[self.nodes[id].id_parent.unwrap()] + 1;
+        }
+
+        // Euler tour: a node is listed when entered and again after each of its children,
+        // the stack holds the nodes being visited with the index of the next child to look at
+        let mut tour = Vec::with_capacity(2 * n - 1);
+        let mut first = vec![0; n];
+        let mut stack = vec![(0, 0)];
+        while let Some((id, next_child)) = stack.pop() {
+            if next_child == 0 {
+                first[id] = tour.len();
+            }
+            tour.push(id);
+
+            let node = &self.nodes[id];
+            let children = [node.id_left, node.id_right];
+            if let Some((i, child_id)) = (next_child..2).find_map(|i| Some((i, children[i]?))) {
+                stack.push((id, i + 1));
+                stack.push((child_id, 0));
+            }
+        }
+
+        // sparse table over the tour: levels[k][i] is the shallowest node of tour[i..i + 2^k]
+        let shallower = |a: usize, b: usize| if depth[b] < depth[a] { b } else { a };
+        let mut levels = vec![tour];
+        while 2 << (levels.len() - 1) <= levels[0].len() {
+            let half = 1 << (levels.len() - 1);
+            let previous = &levels[levels.len() - 1];
+            let level = (0..previous.len() - half)
+                .map(|i| shallower(previous[i], previous[i + half]))
+                .collect();
+            levels.push(level);
+        }
+
+        LcaIndex {
+            first,
+            depth,
+            levels,
+        }
+    }
+}
+
+/// Lowest common ancestors in O(1), on an Euler tour of the tree with a sparse table over it:
+/// the LCA of `u` and `v` is the shallowest node listed between their first occurrences.
+/// Built by `Tree::lca_index`, and not updated if the tree changes afterwards.
+pub struct LcaIndex {
+    first: Vec<usize>, // node id -> first position in the tour
+    depth: Vec<usize>,
+    levels: Vec<Vec<usize>>,
+}
+
+impl LcaIndex {
+    /// Returns the lowest common ancestor of the nodes `u` and `v`.
+    pub fn lca(&self, u: usize, v: usize) -> usize {
+        let (l, r) = (
+            self.first[u].min(self.first[v]),
+            self.first[u].max(self.first[v]),
+        );
+
+        // two overlapping blocks of length 2^k cover tour[l..=r]
+        let k = (r - l + 1).ilog2() as usize;
+        let (a, b) = (self.levels[k][l], self.levels[k][r + 1 - (1 << k)]);
+        if self.depth[b] < self.depth[a] {
+            b
+        } else {
+            a
+        }
+    }
+}
+
+/// Cartesian tree of an array: a min-heap on the values and a BST on the indices,
+/// so that the in-order visit gives back the array. The minimum of any range `[l, r]`
+/// is the lowest common ancestor of the nodes of `l` and `r`.
+pub struct CartesianTree {
+    tree: Tree,
+    lca: LcaIndex,
+    node_of: Vec<usize>,  // array index -> node id
+    index_of: Vec<usize>, // node id -> array index
+}
+
+impl CartesianTree {
+    /// Builds the Cartesian tree of `values` in O(n), and its `LcaIndex` in O(n log n).
+    /// On equal values, the leftmost one is the ancestor.
+    ///
+    /// # Panics
+    /// Panics if `values` is empty.
+    pub fn new(values: &[u32]) -> Self {
+        assert!(
+            !values.is_empty(),
+            "Cannot build a tree from an empty array"
+        );
+
+        let n = values.len();
+        let mut left = vec![None; n];
+        let mut right = vec![None; n];
+
+        // the stack holds the right spine of the tree built so far, values increasing from bottom to top
+        let mut stack: Vec<usize> = Vec::new();
+        for i in 0..n {
+            let mut last_popped = None;
+            while let Some(&top) = stack.last() {
+                if values[top] <= values[i] {
+                    break;
+                }
+                last_popped = stack.pop();
+            }
+
+            // the popped chain becomes the left subtree of i, and i the right child of the new top
+            left[i] = last_popped;
+            if let Some(&top) = stack.last() {
+                right[top] = Some(i);
+            }
+            stack.push(i);
+        }
+
+        // the root must be the node with id 0, so the nodes are numbered in preorder
+        let mut node_of = vec![0; n];
+        let mut index_of = Vec::with_capacity(n);
+        let mut to_visit = vec![stack[0]];
+        while let Some(i) = to_visit.pop() {
+            node_of[i] = index_of.len();
+            index_of.push(i);
+            to_visit.extend(right[i]);
+            to_visit.extend(left[i]);
+        }
+
+        let mut nodes: Vec<Node> = index_of.iter().map(|&i| Node::new(values[i])).collect();
+        for (id, &i) in index_of.iter().enumerate() {
+            nodes[id].id_left = left[i].map(|child| node_of[child]);
+            nodes[id].id_right = right[i].map(|child| node_of[child]);
+            for child_id in [nodes[id].id_left, nodes[id].id_right]
+                .into_iter()
+                .flatten()
+            {
+                nodes[child_id].id_parent = Some(id);
+            }
+        }
+
+        // in preorder children come after their parent, so sizes can be accumulated backwards
+        for id in (1..n).rev() {
+            let parent_id = nodes[id].id_parent.unwrap();
+            nodes[parent_id].size += nodes[id].size;
+        }
+
+        let tree = Tree { nodes };
+        CartesianTree {
+            lca: tree.lca_index(),
+            tree,
+            node_of,
+            index_of,
+        }
+    }
+
+    pub fn tree(&self) -> &Tree {
+        &self.tree
+    }
+
+    /// Returns the id of the node holding the array element at `index`.
+    pub fn node_of(&self, index: usize) -> usize {
+        self.node_of[index]
+    }
+
+    /// Returns the array index of the element held by the node `node_id`.
+    pub fn index_of(&self, node_id: usize) -> usize {
+        self.index_of[node_id]
+    }
+
+    /// Range minimum query on the closed range `[l, r]` of the array, answered as the lowest
+    /// common ancestor of the two endpoints, in O(1). Returns the index of the leftmost minimum and its value.
+    ///
+    /// # Panics
+    /// Panics if `l > r` or `r` is out of the array.
+    pub fn range_min(&self, l: usize, r: usize) -> (usize, u32) {
+        assert!(l <= r && r < self.node_of.len(), "Invalid range");
+
+        let id = self.lca.lca(self.node_of[l], self.node_of[r]);
+        (self.index_of[id], self.tree.nodes[id].key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cartesian_tree() {
+        let values = [5, 2, 8, 1, 9, 3, 3, 7];
+        let cartesian = CartesianTree::new(&values);
+        let tree = cartesian.tree();
+
+        // the minimum is the root, and every node is not smaller than its parent
+        assert_eq!(cartesian.index_of(0), 3);
+        assert_eq!(tree.nodes[0].size, values.len());
+        for node in &tree.nodes {
+            if let Some(parent_id) = node.id_parent {
+                assert!(tree.nodes[parent_id].key <= node.key);
+            }
+        }
+
+        for l in 0..values.len() {
+            for r in l..values.len() {
+                let expected = (l..=r).min_by_key(|&i| (values[i], i)).unwrap();
+                assert_eq!(cartesian.range_min(l, r), (expected, values[expected]));
+            }
+        }
+
+        for (index, &value) in values.iter().enumerate() {
+            let id = cartesian.node_of(index);
+            assert_eq!(tree.nodes[id].key, value);
+            assert_eq!(cartesian.index_of(id), index);
+        }
+    }
+
+    #[test]
+    fn test_lca() {
+        // Tree:
+        //        10
+        //      /    \
+        //     5      15
+        //    / \
+        //   3   7
+        let mut tree = Tree::with_root(10);
+        tree.add_node(0, 5, true); // id 1
+        tree.add_node(0, 15, false); // id 2
+        tree.add_node(1, 3, true); // id 3
+        tree.add_node(1, 7, false); // id 4
+
+        assert_eq!(tree.depth(0), 0);
+        assert_eq!(tree.depth(4), 2);
+        assert_eq!(tree.lca(3, 4), 1);
+        assert_eq!(tree.lca(3, 2), 0);
+        assert_eq!(tree.lca(1, 4), 1);
+        assert_eq!(tree.lca(2, 2), 2);
+
+        let index = tree.lca_index();
+        for u in 0..5 {
+            for v in 0..5 {
+                assert_eq!(index.lca(u, v), tree.lca(u, v));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_min_on_sorted_values() {
+        // sorted values give a chain, the LCA must not climb it
+        let n = 200_000;
+        let values: Vec<u32> = (0..n as u32).collect();
+        let cartesian = CartesianTree::new(&values);
+
+        for l in (0..n).step_by(997) {
+            assert_eq!(cartesian.range_min(l, n - 1), (l, l as u32));
+            assert_eq!(cartesian.range_min(l, l), (l, l as u32));
+        }
+    }
+}
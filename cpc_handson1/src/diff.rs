@@ -0,0 +1,329 @@
+use std::fmt;
+
+use crate::Tree;
+
+/// One difference between two trees aligned by position. `path` is the position of the node
+/// starting from the root, as a string of `L` (left child) and `R` (right child), empty for the root.
+#[derive(Debug, PartialEq, Eq)]
+pub enum DiffEntry {
+    /// Both trees have a node at `path`, with different keys.
+    KeyChanged {
+        path: String,
+        self_id: usize,
+        other_id: usize,
+        old_key: u32,
+        new_key: u32,
+    },
+    /// Only the first tree has a node at `path`: its whole subtree (of `size` nodes) is missing in the other.
+    Removed {
+        path: String,
+        self_id: usize,
+        size: usize,
+    },
+    /// Only the other tree has a node at `path`: its whole subtree (of `size` nodes) is new.
+    Added {
+        path: String,
+        other_id: usize,
+        size: usize,
+    },
+}
+
+/// Structural diff between two trees, see `Tree::diff`.
+/// The entries are listed in preorder of their positions.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TreeDiff {
+    entries: Vec<DiffEntry>,
+}
+
+impl TreeDiff {
+    pub fn entries(&self) -> &[DiffEntry] {
+        &self.entries
+    }
+
+    /// Returns `true` if the two trees have the same shape and the same keys.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Step of the iterative visit in `Tree::diff`. The direction is the move from the parent position,
+/// `None` for the root.
+enum Step {
+    /// Both trees have a node at this position.
+    Both(usize, usize, Option<char>),
+    OnlySelf(usize, char),
+    OnlyOther(usize, char),
+    /// Going back up from a position visited with `Both`.
+    Leave,
+}
+
+impl Tree {
+    /// Compares this tree with `other`, aligning the nodes by their position from the root.
+    /// Reports the nodes whose key changed, and the subtrees that exist in only one of the two trees
+    /// (once, at their root). The visit is iterative and emits the entries directly in preorder, so
+    /// it runs in O(n) plus the length of the reported paths, and deep trees are fine.
+    pub fn diff(&self, other: &Tree) -> TreeDiff {
+        let mut entries = Vec::new();
+        let mut path: Vec<char> = Vec::new(); // moves from the root to the current position
+        let mut to_visit = vec![Step::Both(0, 0, None)];
+
+        // the path of the child of the current position in `direction`
+        let child_path = |path: &[char], direction: char| -> String {
+            path.iter().copied().chain([direction]).collect()
+        };
+
+        while let Some(step) = to_visit.pop() {
+            match step {
+                Step::Both(self_id, other_id, direction) => {
+                    path.extend(direction);
+                    let node = &self.nodes[self_id];
+                    let other_node = &other.nodes[other_id];
+
+                    if node.key != other_node.key {
+                        entries.push(DiffEntry::KeyChanged {
+                            path: path.iter().collect(),
+                            self_id,
+                            other_id,
+                            old_key: node.key,
+                            new_key: other_node.key,
+                        });
+                    }
+
+                    // pushed in reverse so that the left position comes out first
+                    to_visit.push(Step::Leave);
+                    let children = [
+                        (node.id_right, other_node.id_right, 'R'),
+                        (node.id_left, other_node.id_left, 'L'),
+                    ];
+                    for (self_child, other_child, direction) in children {
+                        match (self_child, other_child) {
+                            (Some(self_child), Some(other_child)) => {
+                                to_visit.push(Step::Both(self_child, other_child, Some(direction)))
+                            }
+                            (Some(self_child), None) => {
+                                to_visit.push(Step::OnlySelf(self_child, direction))
+                            }
+                            (None, Some(other_child)) => {
+                                to_visit.push(Step::OnlyOther(other_child, direction))
+                            }
+                            (None, None) => {}
+                        }
+                    }
+                }
+                Step::OnlySelf(self_id, direction) => entries.push(DiffEntry::Removed {
+                    path: child_path(&path, direction),
+                    self_id,
+                    size: self.nodes[self_id].size,
+                }),
+                Step::OnlyOther(other_id, direction) => entries.push(DiffEntry::Added {
+                    path: child_path(&path, direction),
+                    other_id,
+                    size: other.nodes[other_id].size,
+                }),
+                Step::Leave => {
+                    path.pop();
+                }
+            }
+        }
+
+        TreeDiff { entries }
+    }
+}
+
+impl DiffEntry {
+    pub fn path(&self) -> &str {
+        match self {
+            DiffEntry::KeyChanged { path, .. }
+            | DiffEntry::Removed { path, .. }
+            | DiffEntry::Added { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiffEntry::KeyChanged {
+                path,
+                self_id,
+                other_id,
+                old_key,
+                new_key,
+            } => write!(
+                f,
+                "~ {}: key {} -> {} (ids {} / {})",
+                display_path(path),
+                old_key,
+                new_key,
+                self_id,
+                other_id
+            ),
+            DiffEntry::Removed {
+                path,
+                self_id,
+                size,
+            } => write!(
+                f,
+                "- {}: removed subtree of {} node(s) (id {})",
+                display_path(path),
+                size,
+                self_id
+            ),
+            DiffEntry::Added {
+                path,
+                other_id,
+                size,
+            } => write!(
+                f,
+                "+ {}: added subtree of {} node(s) (id {})",
+                display_path(path),
+                size,
+                other_id
+            ),
+        }
+    }
+}
+
+impl fmt::Display for TreeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.entries.is_empty() {
+            return writeln!(f, "trees are identical");
+        }
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    if path.is_empty() {
+        "root"
+    } else {
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::chain;
+
+    #[test]
+    fn test_diff() {
+        // Tree:
+        //        10
+        //      /    \
+        //     5      15
+        //    / \      \
+        //   3   7      20
+        let mut tree = Tree::with_root(10);
+        tree.add_node(0, 5, true); // id 1
+        tree.add_node(0, 15, false); // id 2
+        tree.add_node(1, 3, true); // id 3
+        tree.add_node(1, 7, false); // id 4
+        tree.add_node(2, 20, false); // id 5
+
+        assert!(tree.diff(&tree).is_empty());
+        assert_eq!(tree.diff(&tree).to_string(), "trees are identical\n");
+
+        // Other:
+        //        10
+        //      /    \
+        //     5      16
+        //    /      /
+        //   4      12
+        //  /
+        // 1
+        let mut other = Tree::with_root(10);
+        other.add_node(0, 5, true); // id 1
+        other.add_node(0, 16, false); // id 2
+        other.add_node(2, 12, true); // id 3
+        other.add_node(1, 4, true); // id 4
+        other.add_node(4, 1, true); // id 5
+
+        let diff = tree.diff(&other);
+        assert_eq!(
+            diff.entries(),
+            &[
+                DiffEntry::KeyChanged {
+                    path: "LL".to_string(),
+                    self_id: 3,
+                    other_id: 4,
+                    old_key: 3,
+                    new_key: 4,
+                },
+                DiffEntry::Added {
+                    path: "LLL".to_string(),
+                    other_id: 5,
+                    size: 1,
+                },
+                DiffEntry::Removed {
+                    path: "LR".to_string(),
+                    self_id: 4,
+                    size: 1,
+                },
+                DiffEntry::KeyChanged {
+                    path: "R".to_string(),
+                    self_id: 2,
+                    other_id: 2,
+                    old_key: 15,
+                    new_key: 16,
+                },
+                DiffEntry::Added {
+                    path: "RL".to_string(),
+                    other_id: 3,
+                    size: 1,
+                },
+                DiffEntry::Removed {
+                    path: "RR".to_string(),
+                    self_id: 5,
+                    size: 1,
+                },
+            ]
+        );
+
+        assert_eq!(
+            diff.to_string(),
+            "~ LL: key 3 -> 4 (ids 3 / 4)\n\
+             + LLL: added subtree of 1 node(s) (id 5)\n\
+             - LR: removed subtree of 1 node(s) (id 4)\n\
+             ~ R: key 15 -> 16 (ids 2 / 2)\n\
+             + RL: added subtree of 1 node(s) (id 3)\n\
+             - RR: removed subtree of 1 node(s) (id 5)\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_whole_subtrees() {
+        let tree = Tree::with_root(1);
+
+        let mut other = Tree::with_root(2);
+        let child = other.add_node(0, 3, true);
+        other.add_node(child, 4, false);
+
+        assert_eq!(
+            tree.diff(&other).to_string(),
+            "~ root: key 1 -> 2 (ids 0 / 0)\n+ L: added subtree of 2 node(s) (id 1)\n"
+        );
+        assert_eq!(
+            other.diff(&tree).to_string(),
+            "~ root: key 2 -> 1 (ids 0 / 0)\n- L: removed subtree of 2 node(s) (id 1)\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_on_deep_tree() {
+        // two long left chains that only differ at the bottom
+        let depth = 50_000;
+        let keys: Vec<u32> = (0..depth as u32).collect();
+        let tree = chain(&keys, true);
+        let mut other = chain(&keys, true);
+        other.add_node(depth - 1, 7, false);
+
+        let diff = tree.diff(&other);
+        assert_eq!(diff.entries().len(), 1);
+        let expected_path = "L".repeat(depth - 1) + "R";
+        assert_eq!(diff.entries()[0].path(), expected_path);
+    }
+}
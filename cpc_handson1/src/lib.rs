@@ -1,9 +1,19 @@
 use std::cmp::max;
 
+pub mod cartesian;
+pub mod diff;
+pub mod path_sum;
+#[cfg(test)]
+mod test_util;
+pub mod traversal;
+pub mod treap;
+
 pub struct Node {
     key: u32,
     id_left: Option<usize>,
     id_right: Option<usize>,
+    id_parent: Option<usize>,
+    size: usize, // number of nodes in the subtree rooted at this node
 }
 
 impl Node {
@@ -12,10 +22,14 @@ impl Node {
             key,
             id_left: None,
             id_right: None,
+            id_parent: None,
+            size: 1,
         }
     }
 }
 
+// Every node has a larger id than its parent: `add_node` appends the new node after it,
+// and the other constructors number the nodes in preorder.
 pub struct Tree {
     nodes: Vec<Node>,
 }
@@ -27,7 +41,8 @@ impl Tree {
         }
     }
 
-    /// Adds a child to the node with `parent_id` and returns the id of the new node.
+    /// Adds a child to the node with `parent_id` and returns the id of the new node, in O(depth)
+    /// to keep the subtree size of every ancestor up to date.
     /// The new node has the specified `key`. The new node is the left  child of the  
     /// node `parent_id` iff `is_left` is `true`, the right child otherwise.
     ///
@@ -52,7 +67,9 @@ impl Tree {
         }
 
         let child_id = self.nodes.len();
-        self.nodes.push(Node::new(key));
+        let mut node = Node::new(key);
+        node.id_parent = Some(parent_id);
+        self.nodes.push(node);
 
         let child = if is_left {
             &mut self.nodes[parent_id].id_left
@@ -62,9 +79,44 @@ impl Tree {
 
         *child = Some(child_id);
 
+        // the new node is one more node in the subtree of every ancestor
+        let mut ancestor = Some(parent_id);
+        while let Some(id) = ancestor {
+            self.nodes[id].size += 1;
+            ancestor = self.nodes[id].id_parent;
+        }
+
         child_id
     }
 
+    /// Inserts `key` in the tree following the BST ordering and returns the id of the new node,
+    /// or `None` if the key is already present. Runs in O(height).
+    ///
+    /// The tree is expected to satisfy `is_bst`, otherwise the position of the new node is unspecified.
+    pub fn insert(&mut self, key: u32) -> Option<usize> {
+        let mut id = 0;
+        loop {
+            let node = &self.nodes[id];
+            let (next, is_left) = if key < node.key {
+                (node.id_left, true)
+            } else if key > node.key {
+                (node.id_right, false)
+            } else {
+                return None;
+            };
+
+            match next {
+                Some(next_id) => id = next_id,
+                None => return Some(self.add_node(id, key, is_left)),
+            }
+        }
+    }
+
+    /// Returns the number of nodes in the subtree rooted at `node_id` (0 for a missing child).
+    fn subtree_size(&self, node_id: Option<usize>) -> usize {
+        node_id.map_or(0, |id| self.nodes[id].size)
+    }
+
     /// Returns the sum of all the keys in the tree
     pub fn sum(&self) -> u32 {
         self.rec_sum(Some(0))
@@ -163,6 +215,76 @@ impl Tree {
 
         0
     }
+
+    // ---------------------- ORDER STATISTICS ----------------------
+    // All the queries below assume that the tree satisfies `is_bst` and run in O(height),
+    // thanks to the subtree sizes kept up to date by `add_node` and `insert`.
+
+    /// Returns the `k`-th smallest key of the tree (`k = 1` is the minimum),
+    /// or `None` if `k` is 0 or greater than the number of nodes.
+    pub fn kth_smallest(&self, k: usize) -> Option<u32> {
+        if k == 0 {
+            return None;
+        }
+
+        // number of keys still to skip before reaching the answer
+        let mut remaining = k - 1;
+        let mut node_id = Some(0);
+
+        while let Some(id) = node_id {
+            let node = &self.nodes[id];
+            let left_size = self.subtree_size(node.id_left);
+
+            if remaining < left_size {
+                // the answer is in the left subtree
+                node_id = node.id_left;
+            } else if remaining == left_size {
+                return Some(node.key);
+            } else {
+                // skip the whole left subtree and the current node
+                remaining -= left_size + 1;
+                node_id = node.id_right;
+            }
+        }
+
+        None
+    }
+
+    /// Returns the rank of `key`, i.e. the number of keys in the tree strictly smaller than `key`.
+    /// The key does not need to be in the tree.
+    pub fn rank(&self, key: u32) -> usize {
+        let mut rank = 0;
+        let mut node_id = Some(0);
+
+        while let Some(id) = node_id {
+            let node = &self.nodes[id];
+
+            if key <= node.key {
+                node_id = node.id_left;
+            } else {
+                // the current node and its whole left subtree are smaller than key
+                rank += self.subtree_size(node.id_left) + 1;
+                node_id = node.id_right;
+            }
+        }
+
+        rank
+    }
+
+    /// Returns the number of keys in the closed range `[lo, hi]`.
+    pub fn count_in_range(&self, lo: u32, hi: u32) -> usize {
+        if lo > hi {
+            return 0;
+        }
+
+        // keys <= hi minus keys < lo
+        let up_to_hi = match hi.checked_add(1) {
+            Some(next) => self.rank(next),
+            None => self.nodes[0].size,
+        };
+
+        up_to_hi - self.rank(lo)
+    }
 }
 
 #[cfg(test)]
@@ -315,4 +437,49 @@ mod tests {
 
         assert_eq!(tree.max_path_sum(), 0); // No path between two leaves
     }
+
+    #[test]
+    fn test_order_statistics() {
+        // Tree built with add_node:
+        //        10
+        //      /    \
+        //     5      15
+        //    / \    /  \
+        //   2   7  12  20
+        let mut tree = Tree::with_root(10);
+        tree.add_node(0, 5, true); // id 1
+        tree.add_node(0, 15, false); // id 2
+        tree.add_node(1, 2, true); // id 3
+        tree.add_node(1, 7, false); // id 4
+        tree.add_node(2, 12, true); // id 5
+        tree.add_node(2, 20, false); // id 6
+
+        // Then grown with the BST insert API
+        assert_eq!(tree.insert(13), Some(7));
+        assert_eq!(tree.insert(1), Some(8));
+        assert_eq!(tree.insert(7), None); // already present
+        assert!(tree.is_bst());
+
+        let sorted = [1, 2, 5, 7, 10, 12, 13, 15, 20];
+        for (i, &key) in sorted.iter().enumerate() {
+            assert_eq!(tree.kth_smallest(i + 1), Some(key));
+            assert_eq!(tree.rank(key), i);
+        }
+        assert_eq!(tree.kth_smallest(0), None);
+        assert_eq!(tree.kth_smallest(10), None);
+
+        assert_eq!(tree.rank(0), 0);
+        assert_eq!(tree.rank(11), 5);
+        assert_eq!(tree.rank(u32::MAX), 9);
+
+        assert_eq!(tree.count_in_range(5, 13), 5);
+        assert_eq!(tree.count_in_range(3, 4), 0);
+        assert_eq!(tree.count_in_range(0, u32::MAX), 9);
+        assert_eq!(tree.count_in_range(20, 1), 0);
+
+        // the sizes stay correct when inserts and queries alternate
+        assert_eq!(tree.insert(14), Some(9));
+        assert_eq!(tree.rank(15), 8);
+        assert_eq!(tree.kth_smallest(10), Some(20));
+    }
 }
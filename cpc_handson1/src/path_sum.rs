@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use crate::Tree;
+
+/// A simple path of the tree together with the sum of its keys.
+/// `nodes` lists the node ids from one endpoint of the path to the other.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PathSum {
+    pub sum: u64,
+    pub nodes: Vec<usize>,
+}
+
+/// Step of the explicit-stack depth-first walks below: enter a node, or leave the last entered one.
+enum Visit {
+    Enter(usize),
+    Leave,
+}
+
+impl Tree {
+    /// Returns the root-to-leaf path with the maximum sum, listed from the root down.
+    /// On ties, the leftmost path is returned.
+    pub fn max_root_to_leaf_path(&self) -> PathSum {
+        let gains = self.downward_gains();
+
+        let mut nodes = vec![0];
+        let mut id = 0;
+        while let Some(child_id) = self.best_child(id, &gains) {
+            nodes.push(child_id);
+            id = child_id;
+        }
+
+        PathSum {
+            sum: gains[0],
+            nodes,
+        }
+    }
+
+    /// Returns the path with the maximum sum between any two nodes (possibly the same node),
+    /// listed from its leftmost endpoint to its rightmost one.
+    pub fn max_node_to_node_path(&self) -> PathSum {
+        let gains = self.downward_gains();
+
+        // find the node where the best path bends: its key plus the best chain on both sides
+        let mut best = (0, 0);
+        for (id, node) in self.nodes.iter().enumerate() {
+            let left_gain = node.id_left.map_or(0, |left_id| gains[left_id]);
+            let right_gain = node.id_right.map_or(0, |right_id| gains[right_id]);
+            let sum = left_gain + node.key as u64 + right_gain;
+            if sum > best.0 {
+                best = (sum, id);
+            }
+        }
+
+        let (sum, top_id) = best;
+        let node = &self.nodes[top_id];
+
+        // the left chain is collected top-down, so it is reversed to start from its endpoint
+        let mut nodes = self.best_chain(node.id_left, &gains);
+        nodes.reverse();
+        nodes.push(top_id);
+        nodes.extend(self.best_chain(node.id_right, &gains));
+
+        PathSum { sum, nodes }
+    }
+
+    /// Returns all the downward paths (from a node to one of its descendants, or the node alone)
+    /// whose keys sum to `target`, as `(start_id, end_id)` pairs. The number of such paths is the length
+    /// of the result. Uses prefix sums along the current root path, hashed to find matching starts.
+    pub fn downward_paths_with_sum(&self, target: u64) -> Vec<(usize, usize)> {
+        let mut paths = Vec::new();
+        let mut path: Vec<usize> = Vec::new();
+        let mut path_prefixes: Vec<u64> = Vec::new(); // prefix sum at each node of `path`
+
+        // prefix sum -> positions in `path` of the nodes right after which the prefix has this value,
+        // the empty prefix (before the root) is registered as `usize::MAX`
+        let mut prefixes: HashMap<u64, Vec<usize>> = HashMap::new();
+        prefixes.insert(0, vec![usize::MAX]);
+
+        let mut stack = vec![Visit::Enter(0)];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(id) => {
+                    let node = &self.nodes[id];
+                    let prefix = path_prefixes.last().copied().unwrap_or(0) + node.key as u64;
+                    path.push(id);
+                    path_prefixes.push(prefix);
+
+                    // every earlier prefix equal to `prefix - target` starts a path ending at this node
+                    if let Some(starts) = prefix
+                        .checked_sub(target)
+                        .and_then(|wanted| prefixes.get(&wanted))
+                    {
+                        for &position in starts {
+                            let start_id = if position == usize::MAX {
+                                path[0]
+                            } else {
+                                path[position + 1]
+                            };
+                            paths.push((start_id, id));
+                        }
+                    }
+
+                    prefixes.entry(prefix).or_default().push(path.len() - 1);
+                    self.push_children(id, &mut stack);
+                }
+                Visit::Leave => {
+                    // forget the prefix of the node before going back up
+                    let prefix = path_prefixes.pop().unwrap();
+                    let positions = prefixes.get_mut(&prefix).unwrap();
+                    positions.pop();
+                    if positions.is_empty() {
+                        prefixes.remove(&prefix);
+                    }
+                    path.pop();
+                }
+            }
+        }
+        paths
+    }
+
+    /// Returns all the root-to-leaf paths whose keys sum to `target`, each listed from the root down,
+    /// in left-to-right order.
+    pub fn root_to_leaf_paths_with_sum(&self, target: u64) -> Vec<Vec<usize>> {
+        let mut paths = Vec::new();
+        let mut path: Vec<usize> = Vec::new();
+        let mut path_prefixes: Vec<u64> = Vec::new();
+
+        let mut stack = vec![Visit::Enter(0)];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Enter(id) => {
+                    let node = &self.nodes[id];
+                    let prefix = path_prefixes.last().copied().unwrap_or(0) + node.key as u64;
+
+                    // keys are non-negative, so once the target is exceeded no path below can match
+                    if prefix > target {
+                        continue;
+                    }
+
+                    path.push(id);
+                    path_prefixes.push(prefix);
+                    if node.id_left.is_none() && node.id_right.is_none() && prefix == target {
+                        paths.push(path.clone());
+                    }
+                    self.push_children(id, &mut stack);
+                }
+                Visit::Leave => {
+                    path.pop();
+                    path_prefixes.pop();
+                }
+            }
+        }
+        paths
+    }
+
+    /// Schedules the subtree of `id` after entering it: its children left first, then leaving it.
+    fn push_children(&self, id: usize, stack: &mut Vec<Visit>) {
+        let node = &self.nodes[id];
+        stack.push(Visit::Leave);
+        if let Some(right_id) = node.id_right {
+            stack.push(Visit::Enter(right_id));
+        }
+        if let Some(left_id) = node.id_left {
+            stack.push(Visit::Enter(left_id));
+        }
+    }
+
+    /// Computes, for every node, the maximum sum of a path going down from it to a leaf.
+    fn downward_gains(&self) -> Vec<u64> {
+        let mut gains = vec![0; self.nodes.len()];
+
+        // reversed preorder: every node comes after its children
+        let order: Vec<usize> = self.preorder().collect();
+        for &id in order.iter().rev() {
+            let node = &self.nodes[id];
+            let left_gain = node.id_left.map_or(0, |left_id| gains[left_id]);
+            let right_gain = node.id_right.map_or(0, |right_id| gains[right_id]);
+            gains[id] = node.key as u64 + left_gain.max(right_gain);
+        }
+        gains
+    }
+
+    /// Returns the child of `id` with the largest downward gain (the left one on ties), if any.
+    fn best_child(&self, id: usize, gains: &[u64]) -> Option<usize> {
+        let node = &self.nodes[id];
+        match (node.id_left, node.id_right) {
+            (Some(left_id), Some(right_id)) => {
+                if gains[right_id] > gains[left_id] {
+                    Some(right_id)
+                } else {
+                    Some(left_id)
+                }
+            }
+            (left_id, right_id) => left_id.or(right_id),
+        }
+    }
+
+    /// Returns the chain of nodes starting at `node_id` that realizes its downward gain, top-down.
+    /// Nodes that would not increase the sum are left out, so the chain stops at the last positive key.
+    fn best_chain(&self, node_id: Option<usize>, gains: &[u64]) -> Vec<usize> {
+        let mut chain = Vec::new();
+        let mut current = node_id;
+        while let Some(id) = current {
+            if gains[id] == 0 {
+                break;
+            }
+            chain.push(id);
+            current = self.best_child(id, gains);
+        }
+        chain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::chain;
+
+    // Tree:
+    //        10
+    //      /    \
+    //     5      15
+    //    / \    /  \
+    //   3   7  12  20
+    //  /             \
+    // 1               25
+    fn sample_tree() -> Tree {
+        let mut tree = Tree::with_root(10);
+        tree.add_node(0, 5, true); // id 1
+        tree.add_node(0, 15, false); // id 2
+        tree.add_node(1, 3, true); // id 3
+        tree.add_node(3, 1, true); // id 4
+        tree.add_node(1, 7, false); // id 5
+        tree.add_node(2, 12, true); // id 6
+        tree.add_node(2, 20, false); // id 7
+        tree.add_node(7, 25, false); // id 8
+        tree
+    }
+
+    #[test]
+    fn test_max_paths() {
+        let tree = sample_tree();
+
+        assert_eq!(
+            tree.max_root_to_leaf_path(),
+            PathSum {
+                sum: 70,
+                nodes: vec![0, 2, 7, 8]
+            }
+        );
+
+        // same path as max_path_sum, endpoints included
+        assert_eq!(
+            tree.max_node_to_node_path(),
+            PathSum {
+                sum: tree.max_path_sum() as u64,
+                nodes: vec![5, 1, 0, 2, 7, 8]
+            }
+        );
+
+        let tree = Tree::with_root(4);
+        assert_eq!(
+            tree.max_node_to_node_path(),
+            PathSum {
+                sum: 4,
+                nodes: vec![0]
+            }
+        );
+    }
+
+    #[test]
+    fn test_downward_paths_with_sum() {
+        let tree = sample_tree();
+
+        let mut paths = tree.downward_paths_with_sum(15);
+        paths.sort();
+        // 10 -> 5 and 15 alone
+        assert_eq!(paths, vec![(0, 1), (2, 2)]);
+
+        let mut paths = tree.downward_paths_with_sum(45);
+        paths.sort();
+        // 10 -> 15 -> 20 and 20 -> 25
+        assert_eq!(paths, vec![(0, 7), (7, 8)]);
+
+        assert!(tree.downward_paths_with_sum(1000).is_empty());
+    }
+
+    #[test]
+    fn test_downward_paths_with_zero_keys() {
+        // Tree:
+        //   0
+        //   |
+        //   0
+        //   |
+        //   2
+        let mut tree = Tree::with_root(0);
+        tree.add_node(0, 0, true);
+        tree.add_node(1, 2, true);
+
+        let mut paths = tree.downward_paths_with_sum(0);
+        paths.sort();
+        assert_eq!(paths, vec![(0, 0), (0, 1), (1, 1)]);
+
+        let mut paths = tree.downward_paths_with_sum(2);
+        paths.sort();
+        assert_eq!(paths, vec![(0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn test_root_to_leaf_paths_with_sum() {
+        let tree = sample_tree();
+
+        assert_eq!(tree.root_to_leaf_paths_with_sum(19), vec![vec![0, 1, 3, 4]]);
+        assert_eq!(tree.root_to_leaf_paths_with_sum(22), vec![vec![0, 1, 5]]);
+        assert!(tree.root_to_leaf_paths_with_sum(15).is_empty());
+
+        // Tree with two matching paths:
+        //     1
+        //    / \
+        //   2   2
+        let mut tree = Tree::with_root(1);
+        tree.add_node(0, 2, true);
+        tree.add_node(0, 2, false);
+        assert_eq!(
+            tree.root_to_leaf_paths_with_sum(3),
+            vec![vec![0, 1], vec![0, 2]]
+        );
+    }
+
+    #[test]
+    fn test_path_sums_on_deep_tree() {
+        // a right-leaning chain deep enough to overflow the stack with recursive traversals
+        let depth = 100_000;
+        let tree = chain(&vec![1; depth], false);
+
+        assert_eq!(tree.downward_paths_with_sum(depth as u64).len(), 1);
+        assert_eq!(tree.downward_paths_with_sum(1).len(), depth);
+        assert_eq!(
+            tree.root_to_leaf_paths_with_sum(depth as u64)[0].len(),
+            depth
+        );
+        assert_eq!(tree.max_root_to_leaf_path().sum, depth as u64);
+        assert_eq!(tree.max_node_to_node_path().nodes.len(), depth);
+    }
+}
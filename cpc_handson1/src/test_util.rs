@@ -0,0 +1,34 @@
+use crate::{Node, Tree};
+
+/// Small deterministic generator for the randomized tests.
+pub struct Lcg(pub u64);
+
+impl Lcg {
+    /// Next value, uniform enough in `0..bound`.
+    pub fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % bound
+    }
+}
+
+/// Chain of nodes with the given `keys` from the root down, every node being the left child of the
+/// previous one iff `is_left`. Built directly in O(n), where `add_node` would take O(n^2).
+pub fn chain(keys: &[u32], is_left: bool) -> Tree {
+    let n = keys.len();
+    let mut nodes: Vec<Node> = keys.iter().map(|&key| Node::new(key)).collect();
+    for (id, node) in nodes.iter_mut().enumerate() {
+        node.size = n - id;
+        node.id_parent = id.checked_sub(1);
+        if id + 1 < n {
+            if is_left {
+                node.id_left = Some(id + 1);
+            } else {
+                node.id_right = Some(id + 1);
+            }
+        }
+    }
+    Tree { nodes }
+}
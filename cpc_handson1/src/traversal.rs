@@ -0,0 +1,271 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::Tree;
+
+/// Non-recursive breadth-first iterator over a `Tree`.
+/// Yields `(node_id, depth, column)`, where the root is at depth 0 and column 0,
+/// a left child is one column to the left of its parent and a right child one column to the right.
+pub struct LevelOrder<'a> {
+    tree: &'a Tree,
+    queue: VecDeque<(usize, usize, isize)>,
+}
+
+impl Iterator for LevelOrder<'_> {
+    type Item = (usize, usize, isize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, depth, column) = self.queue.pop_front()?;
+        let node = &self.tree.nodes[id];
+
+        if let Some(left_id) = node.id_left {
+            self.queue.push_back((left_id, depth + 1, column - 1));
+        }
+        if let Some(right_id) = node.id_right {
+            self.queue.push_back((right_id, depth + 1, column + 1));
+        }
+
+        Some((id, depth, column))
+    }
+}
+
+/// Non-recursive depth-first iterator over a `Tree`, yielding node ids in preorder.
+pub struct PreOrder<'a> {
+    tree: &'a Tree,
+    stack: Vec<usize>,
+}
+
+impl Iterator for PreOrder<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.stack.pop()?;
+        let node = &self.tree.nodes[id];
+
+        // push the right child first so that the left subtree is visited first
+        if let Some(right_id) = node.id_right {
+            self.stack.push(right_id);
+        }
+        if let Some(left_id) = node.id_left {
+            self.stack.push(left_id);
+        }
+
+        Some(id)
+    }
+}
+
+impl Tree {
+    /// Returns a breadth-first iterator over the tree, see `LevelOrder`.
+    pub fn level_order(&self) -> LevelOrder<'_> {
+        LevelOrder {
+            tree: self,
+            queue: VecDeque::from([(0, 0, 0)]),
+        }
+    }
+
+    /// Returns a preorder iterator over the tree, see `PreOrder`.
+    pub fn preorder(&self) -> PreOrder<'_> {
+        PreOrder {
+            tree: self,
+            stack: vec![0],
+        }
+    }
+
+    fn is_leaf(&self, id: usize) -> bool {
+        self.nodes[id].id_left.is_none() && self.nodes[id].id_right.is_none()
+    }
+
+    /// Returns the node ids grouped by level, each level from left to right.
+    pub fn levels(&self) -> Vec<Vec<usize>> {
+        let mut levels: Vec<Vec<usize>> = Vec::new();
+        for (id, depth, _) in self.level_order() {
+            if depth == levels.len() {
+                levels.push(Vec::new());
+            }
+            levels[depth].push(id);
+        }
+        levels
+    }
+
+    /// Returns the levels of the tree alternating direction: the first level from left to right,
+    /// the second from right to left, and so on.
+    pub fn zigzag_level_order(&self) -> Vec<Vec<usize>> {
+        let mut levels = self.levels();
+        for level in levels.iter_mut().skip(1).step_by(2) {
+            level.reverse();
+        }
+        levels
+    }
+
+    /// Returns the node ids grouped by column, from the leftmost column to the rightmost one.
+    /// Inside a column, nodes are sorted by depth, and nodes at the same depth from left to right.
+    pub fn vertical_order(&self) -> Vec<Vec<usize>> {
+        let mut columns: BTreeMap<isize, Vec<usize>> = BTreeMap::new();
+        for (id, _, column) in self.level_order() {
+            columns.entry(column).or_default().push(id);
+        }
+        columns.into_values().collect()
+    }
+
+    /// Returns the first node of every level, from the root down.
+    pub fn left_view(&self) -> Vec<usize> {
+        self.levels().iter().map(|level| level[0]).collect()
+    }
+
+    /// Returns the last node of every level, from the root down.
+    pub fn right_view(&self) -> Vec<usize> {
+        self.levels()
+            .iter()
+            .map(|level| level[level.len() - 1])
+            .collect()
+    }
+
+    /// Returns the nodes seen when looking at the tree from above: the shallowest node
+    /// of every column, from left to right.
+    pub fn top_view(&self) -> Vec<usize> {
+        self.vertical_order()
+            .iter()
+            .map(|column| column[0])
+            .collect()
+    }
+
+    /// Returns the nodes seen when looking at the tree from below: the deepest node of every column,
+    /// from left to right. When two nodes share the deepest level of a column, the rightmost one wins.
+    pub fn bottom_view(&self) -> Vec<usize> {
+        self.vertical_order()
+            .iter()
+            .map(|column| column[column.len() - 1])
+            .collect()
+    }
+
+    /// Returns the boundary of the tree counter-clockwise starting from the root: the root,
+    /// the left boundary top-down, all the leaves from left to right, then the right boundary bottom-up.
+    /// Each node appears once.
+    pub fn boundary_traversal(&self) -> Vec<usize> {
+        let mut boundary = vec![0];
+        if self.is_leaf(0) {
+            return boundary;
+        }
+
+        // left boundary, excluding the leaf it ends on
+        let mut node_id = self.nodes[0].id_left;
+        while let Some(id) = node_id {
+            if self.is_leaf(id) {
+                break;
+            }
+            boundary.push(id);
+            node_id = self.nodes[id].id_left.or(self.nodes[id].id_right);
+        }
+
+        // leaves from left to right
+        boundary.extend(self.preorder().filter(|&id| id != 0 && self.is_leaf(id)));
+
+        // right boundary, excluding the leaf it ends on, collected top-down then reversed
+        let mut right_boundary = Vec::new();
+        let mut node_id = self.nodes[0].id_right;
+        while let Some(id) = node_id {
+            if self.is_leaf(id) {
+                break;
+            }
+            right_boundary.push(id);
+            node_id = self.nodes[id].id_right.or(self.nodes[id].id_left);
+        }
+        boundary.extend(right_boundary.into_iter().rev());
+
+        boundary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::chain;
+
+    // Tree:
+    //          1
+    //        /   \
+    //       2     3
+    //      / \     \
+    //     4   5     6
+    //        / \   /
+    //       7   8 9
+    fn sample_tree() -> Tree {
+        let mut tree = Tree::with_root(1);
+        tree.add_node(0, 2, true); // id 1
+        tree.add_node(0, 3, false); // id 2
+        tree.add_node(1, 4, true); // id 3
+        tree.add_node(1, 5, false); // id 4
+        tree.add_node(2, 6, false); // id 5
+        tree.add_node(4, 7, true); // id 6
+        tree.add_node(4, 8, false); // id 7
+        tree.add_node(5, 9, true); // id 8
+        tree
+    }
+
+    #[test]
+    fn test_level_orders() {
+        let tree = sample_tree();
+
+        assert_eq!(
+            tree.preorder().collect::<Vec<_>>(),
+            vec![0, 1, 3, 4, 6, 7, 2, 5, 8]
+        );
+        assert_eq!(
+            tree.levels(),
+            vec![vec![0], vec![1, 2], vec![3, 4, 5], vec![6, 7, 8]]
+        );
+        assert_eq!(
+            tree.zigzag_level_order(),
+            vec![vec![0], vec![2, 1], vec![3, 4, 5], vec![8, 7, 6]]
+        );
+    }
+
+    #[test]
+    fn test_vertical_order_and_views() {
+        let tree = sample_tree();
+
+        // columns: -2: [3], -1: [1, 6], 0: [0, 4], 1: [2, 7, 8], 2: [5]
+        assert_eq!(
+            tree.vertical_order(),
+            vec![vec![3], vec![1, 6], vec![0, 4], vec![2, 7, 8], vec![5]]
+        );
+
+        assert_eq!(tree.left_view(), vec![0, 1, 3, 6]);
+        assert_eq!(tree.right_view(), vec![0, 2, 5, 8]);
+        assert_eq!(tree.top_view(), vec![3, 1, 0, 2, 5]);
+        assert_eq!(tree.bottom_view(), vec![3, 6, 4, 8, 5]);
+    }
+
+    #[test]
+    fn test_boundary_traversal() {
+        let tree = sample_tree();
+        // root, left boundary (2), leaves (4, 7, 8, 9), right boundary bottom-up (6, 3)
+        assert_eq!(tree.boundary_traversal(), vec![0, 1, 3, 6, 7, 8, 5, 2]);
+
+        let tree = Tree::with_root(1);
+        assert_eq!(tree.boundary_traversal(), vec![0]);
+
+        // Tree:
+        //   1
+        //    \
+        //     2
+        //      \
+        //       3
+        let mut tree = Tree::with_root(1);
+        tree.add_node(0, 2, false);
+        tree.add_node(1, 3, false);
+        assert_eq!(tree.boundary_traversal(), vec![0, 2, 1]);
+    }
+
+    #[test]
+    fn test_views_on_deep_tree() {
+        // a left-leaning chain deep enough to overflow the stack with recursive traversals
+        let depth = 20_000;
+        let keys: Vec<u32> = (0..depth as u32).collect();
+        let tree = chain(&keys, true);
+
+        assert_eq!(tree.levels().len(), depth);
+        assert_eq!(tree.left_view().len(), depth);
+        assert_eq!(tree.top_view().len(), depth);
+        assert_eq!(tree.boundary_traversal().len(), depth);
+    }
+}
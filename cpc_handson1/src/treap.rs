@@ -0,0 +1,388 @@
+use std::ops::Range;
+
+use crate::{Node, Tree};
+
+/// Randomized treap with implicit keys: it stores a sequence, and the position of an element is
+/// the number of elements before it in the in-order visit (the size of the left subtrees on its path).
+/// The nodes live in the same kind of arena as `Tree`, the heap priorities and the pending
+/// reversals are kept in parallel vectors. All the operations run in expected O(log n).
+///
+/// Parent links (`id_parent`) are not maintained while splitting and merging, they are only
+/// rebuilt by `to_tree`.
+pub struct ImplicitTreap {
+    nodes: Vec<Node>,
+    priority: Vec<u64>,
+    reversed: Vec<bool>, // pending reversal of the subtree, not yet applied to the children
+    root: Option<usize>,
+    free: Vec<usize>, // ids of removed nodes that can be reused
+    seed: u64,
+}
+
+impl ImplicitTreap {
+    /// Creates an empty treap. The `seed` drives the random priorities.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            nodes: Vec::new(),
+            priority: Vec::new(),
+            reversed: Vec::new(),
+            root: None,
+            free: Vec::new(),
+            // xorshift gets stuck on 0
+            seed: seed.max(1),
+        }
+    }
+
+    /// Creates a treap holding the sequence `values`.
+    pub fn from_values(values: &[u32], seed: u64) -> Self {
+        let mut treap = Self::new(seed);
+        for (position, &value) in values.iter().enumerate() {
+            treap.insert(position, value);
+        }
+        treap
+    }
+
+    pub fn len(&self) -> usize {
+        self.size(self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Inserts `key` so that it ends up at `position`, shifting the following elements to the right.
+    ///
+    /// # Panics
+    /// Panics if `position` is greater than the length of the sequence.
+    pub fn insert(&mut self, position: usize, key: u32) {
+        assert!(position <= self.len(), "Position is out of range");
+
+        let id = self.new_node(key);
+        let (left, right) = self.split(self.root, position);
+        let left = self.merge(left, Some(id));
+        self.root = self.merge(left, right);
+    }
+
+    /// Removes the elements in `range` and returns them in order.
+    ///
+    /// # Panics
+    /// Panics if the range is decreasing or goes past the end of the sequence.
+    pub fn remove_range(&mut self, range: Range<usize>) -> Vec<u32> {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "Range is out of bounds"
+        );
+
+        let (left, rest) = self.split(self.root, range.start);
+        let (middle, right) = self.split(rest, range.end - range.start);
+        self.root = self.merge(left, right);
+
+        let removed = self.collect(middle);
+
+        // recycle the removed nodes
+        let mut to_free: Vec<usize> = middle.into_iter().collect();
+        while let Some(id) = to_free.pop() {
+            to_free.extend(self.nodes[id].id_left);
+            to_free.extend(self.nodes[id].id_right);
+            self.free.push(id);
+        }
+
+        removed
+    }
+
+    /// Reverses the order of the elements in `range`.
+    ///
+    /// # Panics
+    /// Panics if the range is decreasing or goes past the end of the sequence.
+    pub fn reverse_range(&mut self, range: Range<usize>) {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "Range is out of bounds"
+        );
+
+        let (left, rest) = self.split(self.root, range.start);
+        let (middle, right) = self.split(rest, range.end - range.start);
+        if let Some(id) = middle {
+            self.reversed[id] = !self.reversed[id];
+        }
+        let left = self.merge(left, middle);
+        self.root = self.merge(left, right);
+    }
+
+    /// Returns the element at `position`, if any.
+    pub fn get(&mut self, position: usize) -> Option<u32> {
+        let mut remaining = position;
+        let mut node_id = self.root;
+
+        while let Some(id) = node_id {
+            self.push_down(id);
+            let left_size = self.size(self.nodes[id].id_left);
+
+            if remaining < left_size {
+                node_id = self.nodes[id].id_left;
+            } else if remaining == left_size {
+                return Some(self.nodes[id].key);
+            } else {
+                remaining -= left_size + 1;
+                node_id = self.nodes[id].id_right;
+            }
+        }
+
+        None
+    }
+
+    /// Returns the whole sequence in order.
+    pub fn to_vec(&mut self) -> Vec<u32> {
+        self.collect(self.root)
+    }
+
+    /// Copies the treap into a `Tree` with the same shape (root at id 0, subtree sizes and parents set),
+    /// or returns `None` if the treap is empty. The keys of the tree are the stored values.
+    pub fn to_tree(&mut self) -> Option<Tree> {
+        let root = self.root?;
+
+        // number the nodes in preorder so that the root gets id 0
+        let mut nodes: Vec<Node> = Vec::with_capacity(self.len());
+        let mut to_visit = vec![(root, None, false)];
+        while let Some((id, parent_id, is_left)) = to_visit.pop() {
+            self.push_down(id);
+
+            let new_id = nodes.len();
+            let mut node = Node::new(self.nodes[id].key);
+            node.size = self.nodes[id].size;
+            node.id_parent = parent_id;
+            nodes.push(node);
+
+            if let Some(parent_id) = parent_id {
+                if is_left {
+                    nodes[parent_id].id_left = Some(new_id);
+                } else {
+                    nodes[parent_id].id_right = Some(new_id);
+                }
+            }
+
+            to_visit.extend(
+                self.nodes[id]
+                    .id_right
+                    .map(|child| (child, Some(new_id), false)),
+            );
+            to_visit.extend(
+                self.nodes[id]
+                    .id_left
+                    .map(|child| (child, Some(new_id), true)),
+            );
+        }
+
+        Some(Tree { nodes })
+    }
+
+    // ---------------------- SPLIT / MERGE ----------------------
+
+    /// Splits the subtree `node_id` into the first `k` elements and the remaining ones.
+    fn split(&mut self, node_id: Option<usize>, k: usize) -> (Option<usize>, Option<usize>) {
+        let Some(id) = node_id else {
+            return (None, None);
+        };
+        self.push_down(id);
+
+        let left_size = self.size(self.nodes[id].id_left);
+        if k <= left_size {
+            // the split point is in the left subtree, the current node goes to the right part
+            let (left, right) = self.split(self.nodes[id].id_left, k);
+            self.nodes[id].id_left = right;
+            self.update_size(id);
+            (left, Some(id))
+        } else {
+            let (left, right) = self.split(self.nodes[id].id_right, k - left_size - 1);
+            self.nodes[id].id_right = left;
+            self.update_size(id);
+            (Some(id), right)
+        }
+    }
+
+    /// Concatenates the sequences of the subtrees `left` and `right`.
+    fn merge(&mut self, left: Option<usize>, right: Option<usize>) -> Option<usize> {
+        match (left, right) {
+            (None, other) | (other, None) => other,
+            (Some(left_id), Some(right_id)) => {
+                // the node with the highest priority becomes the root
+                if self.priority[left_id] > self.priority[right_id] {
+                    self.push_down(left_id);
+                    self.nodes[left_id].id_right = self.merge(self.nodes[left_id].id_right, right);
+                    self.update_size(left_id);
+                    Some(left_id)
+                } else {
+                    self.push_down(right_id);
+                    self.nodes[right_id].id_left = self.merge(left, self.nodes[right_id].id_left);
+                    self.update_size(right_id);
+                    Some(right_id)
+                }
+            }
+        }
+    }
+
+    // ---------------------- HELPERS ----------------------
+
+    fn new_node(&mut self, key: u32) -> usize {
+        let priority = self.next_random();
+        match self.free.pop() {
+            Some(id) => {
+                self.nodes[id] = Node::new(key);
+                self.priority[id] = priority;
+                self.reversed[id] = false;
+                id
+            }
+            None => {
+                self.nodes.push(Node::new(key));
+                self.priority.push(priority);
+                self.reversed.push(false);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn size(&self, node_id: Option<usize>) -> usize {
+        node_id.map_or(0, |id| self.nodes[id].size)
+    }
+
+    fn update_size(&mut self, id: usize) {
+        self.nodes[id].size =
+            self.size(self.nodes[id].id_left) + self.size(self.nodes[id].id_right) + 1;
+    }
+
+    // apply the pending reversal of a node: swap its children and pass the reversal down to them
+    fn push_down(&mut self, id: usize) {
+        if self.reversed[id] {
+            let node = &mut self.nodes[id];
+            std::mem::swap(&mut node.id_left, &mut node.id_right);
+
+            for child_id in [node.id_left, node.id_right].into_iter().flatten() {
+                self.reversed[child_id] = !self.reversed[child_id];
+            }
+            self.reversed[id] = false;
+        }
+    }
+
+    // in-order visit of the subtree, applying pending reversals on the way
+    fn collect(&mut self, node_id: Option<usize>) -> Vec<u32> {
+        let mut keys = Vec::with_capacity(self.size(node_id));
+        let mut stack = Vec::new();
+        let mut current = node_id;
+
+        while current.is_some() || !stack.is_empty() {
+            while let Some(id) = current {
+                self.push_down(id);
+                stack.push(id);
+                current = self.nodes[id].id_left;
+            }
+            let id = stack.pop().unwrap();
+            keys.push(self.nodes[id].key);
+            current = self.nodes[id].id_right;
+        }
+
+        keys
+    }
+
+    // xorshift64, good enough for treap priorities
+    fn next_random(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    #[test]
+    fn test_treap_sequence_operations() {
+        let mut treap = ImplicitTreap::from_values(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9], 42);
+        let mut expected: Vec<u32> = (0..10).collect();
+        assert_eq!(treap.to_vec(), expected);
+
+        treap.insert(3, 100);
+        expected.insert(3, 100);
+        treap.insert(11, 200);
+        expected.insert(11, 200);
+        assert_eq!(treap.to_vec(), expected);
+
+        treap.reverse_range(2..8);
+        expected[2..8].reverse();
+        assert_eq!(treap.to_vec(), expected);
+
+        assert_eq!(
+            treap.remove_range(1..4),
+            expected.drain(1..4).collect::<Vec<_>>()
+        );
+        assert_eq!(treap.to_vec(), expected);
+
+        treap.reverse_range(0..treap.len());
+        expected.reverse();
+        assert_eq!(treap.to_vec(), expected);
+
+        for (position, &value) in expected.iter().enumerate() {
+            assert_eq!(treap.get(position), Some(value));
+        }
+        assert_eq!(treap.get(expected.len()), None);
+        assert_eq!(treap.len(), expected.len());
+    }
+
+    #[test]
+    fn test_treap_random_operations() {
+        let mut treap = ImplicitTreap::new(7);
+        let mut expected: Vec<u32> = Vec::new();
+
+        let mut rng = Lcg(12345);
+
+        for step in 0..2000 {
+            match rng.next(3) {
+                0 => {
+                    let position = rng.next(expected.len() + 1);
+                    treap.insert(position, step);
+                    expected.insert(position, step);
+                }
+                1 => {
+                    let a = rng.next(expected.len() + 1);
+                    let b = rng.next(expected.len() + 1);
+                    let range = a.min(b)..a.max(b);
+                    treap.reverse_range(range.clone());
+                    expected[range].reverse();
+                }
+                _ => {
+                    let a = rng.next(expected.len() + 1);
+                    let b = rng.next(expected.len() + 1);
+                    let range = a.min(b)..(a.min(b) + (a.max(b) - a.min(b)) / 4);
+                    let removed = treap.remove_range(range.clone());
+                    assert_eq!(removed, expected.drain(range).collect::<Vec<_>>());
+                }
+            }
+        }
+
+        assert_eq!(treap.to_vec(), expected);
+
+        // the treap converted to a Tree keeps the sequence in its in-order visit
+        let tree = treap.to_tree().unwrap();
+        assert_eq!(tree.nodes[0].size, expected.len());
+        let in_order: Vec<u32> = (1..=expected.len())
+            .map(|k| {
+                // kth node by position: walk with the subtree sizes
+                let mut remaining = k - 1;
+                let mut id = 0;
+                loop {
+                    let left_size = tree.subtree_size(tree.nodes[id].id_left);
+                    if remaining < left_size {
+                        id = tree.nodes[id].id_left.unwrap();
+                    } else if remaining == left_size {
+                        break tree.nodes[id].key;
+                    } else {
+                        remaining -= left_size + 1;
+                        id = tree.nodes[id].id_right.unwrap();
+                    }
+                }
+            })
+            .collect();
+        assert_eq!(in_order, expected);
+    }
+}
@@ -0,0 +1,145 @@
+// Compares the recursive `SegmentTree` with the bottom-up `IterativeSegmentTree` on the same workload:
+// build, range additions, range sums and point reads on random ranges.
+//
+// Run with `cargo bench`, optionally passing the array length and the number of operations:
+// `cargo bench -- 10000000 1000000`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use cpc_handson2::iterative::IterativeSegmentTree;
+use cpc_handson2::lazy::Add;
+use cpc_handson2::monoid::Sum;
+use cpc_handson2::SegmentTree;
+
+// small deterministic generator, so that both trees see the same operations
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % bound
+    }
+
+    fn range(&mut self, n: usize) -> (usize, usize) {
+        let x = self.next(n + 1);
+        let y = self.next(n + 1);
+        (x.min(y), x.max(y))
+    }
+}
+
+// the operations shared by the two trees
+trait Bench {
+    fn name() -> &'static str;
+    fn build(a: &[i64]) -> Self;
+    fn add(&mut self, l: usize, r: usize, delta: i64);
+    fn sum(&self, l: usize, r: usize) -> i64;
+    fn get(&self, i: usize) -> i64;
+}
+
+impl Bench for SegmentTree<Sum, Add> {
+    fn name() -> &'static str {
+        "recursive"
+    }
+
+    fn build(a: &[i64]) -> Self {
+        SegmentTree::init(a)
+    }
+
+    fn add(&mut self, l: usize, r: usize, delta: i64) {
+        self.add_range(l..r, delta).unwrap();
+    }
+
+    fn sum(&self, l: usize, r: usize) -> i64 {
+        self.query(l..r).unwrap()
+    }
+
+    fn get(&self, i: usize) -> i64 {
+        SegmentTree::get(self, i).unwrap()
+    }
+}
+
+impl Bench for IterativeSegmentTree<Sum, Add> {
+    fn name() -> &'static str {
+        "iterative"
+    }
+
+    fn build(a: &[i64]) -> Self {
+        IterativeSegmentTree::init(a)
+    }
+
+    fn add(&mut self, l: usize, r: usize, delta: i64) {
+        self.add_range(l..r, delta).unwrap();
+    }
+
+    fn sum(&self, l: usize, r: usize) -> i64 {
+        self.query(l..r).unwrap()
+    }
+
+    fn get(&self, i: usize) -> i64 {
+        IterativeSegmentTree::get(self, i).unwrap()
+    }
+}
+
+fn run<T: Bench>(a: &[i64], operations: usize) {
+    let n = a.len();
+    let mut rng = Lcg(42);
+
+    let start = Instant::now();
+    let mut tree = T::build(black_box(a));
+    let build = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..operations {
+        let (l, r) = rng.range(n);
+        tree.add(l, r, rng.next(100) as i64);
+    }
+    let add = start.elapsed();
+
+    let start = Instant::now();
+    let mut checksum = 0i64;
+    for _ in 0..operations {
+        let (l, r) = rng.range(n);
+        checksum = checksum.wrapping_add(tree.sum(l, r));
+    }
+    let sum = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..operations {
+        checksum = checksum.wrapping_add(tree.get(rng.next(n)));
+    }
+    let get = start.elapsed();
+
+    println!(
+        "{:>10}: build {:>9}  add_range {:>9}  query {:>9}  get {:>9}  (checksum {})",
+        T::name(),
+        format_duration(build),
+        format_duration(add),
+        format_duration(sum),
+        format_duration(get),
+        black_box(checksum)
+    );
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn main() {
+    // `cargo bench` passes `--bench` to the binary, keep only the numbers
+    let numbers: Vec<usize> = std::env::args()
+        .skip(1)
+        .filter_map(|arg| arg.parse().ok())
+        .collect();
+    let n = numbers.first().copied().unwrap_or(1 << 20);
+    let operations = numbers.get(1).copied().unwrap_or(200_000);
+
+    let a: Vec<i64> = (0..n as i64).map(|i| (i * 31) % 1000).collect();
+
+    println!("n = {}, {} operations of each kind", n, operations);
+    run::<SegmentTree<Sum, Add>>(&a, operations);
+    run::<IterativeSegmentTree<Sum, Add>>(&a, operations);
+}
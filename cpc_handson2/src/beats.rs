@@ -0,0 +1,389 @@
+use std::ops::RangeBounds;
+
+use crate::range::{to_closed, RangeError};
+use crate::{left_child, right_child};
+
+// sentinels for "no second maximum / minimum"
+const NEG_INF: i64 = i64::MIN;
+const INF: i64 = i64::MAX;
+
+// Segment tree beats (Ji Ruyi's technique): besides the sum, every node keeps its maximum, the number of
+// times it occurs and the strict second maximum (and the same for the minimum). A range chmin only has to
+// go below a node when the new bound is not greater than its second maximum, which bounds the total work
+// to amortized O(log^2 n) per operation even when mixed with range additions.
+pub struct SegmentTreeBeats {
+    n: usize,
+    sum: Vec<i64>,
+    max1: Vec<i64>,
+    max2: Vec<i64>,
+    max_count: Vec<usize>,
+    min1: Vec<i64>,
+    min2: Vec<i64>,
+    min_count: Vec<usize>,
+    lazy_add: Vec<i64>,
+}
+
+impl SegmentTreeBeats {
+    // --------------------- CONSTRUCTOR ---------------------
+
+    pub fn init(a: &[i64]) -> Self {
+        let length = a.len();
+        let size = 4 * length;
+
+        let mut beats = SegmentTreeBeats {
+            n: length,
+            sum: vec![0; size],
+            max1: vec![NEG_INF; size],
+            max2: vec![NEG_INF; size],
+            max_count: vec![0; size],
+            min1: vec![INF; size],
+            min2: vec![INF; size],
+            min_count: vec![0; size],
+            lazy_add: vec![0; size],
+        };
+
+        // an empty tree has no node: every range of it is empty, and every index out of bounds
+        if length > 0 {
+            beats.build(a, 0, length - 1, 0);
+        }
+        beats
+    }
+
+    fn build(&mut self, arr: &[i64], start: usize, end: usize, node: usize) {
+        if start == end {
+            self.sum[node] = arr[start];
+            self.max1[node] = arr[start];
+            self.max_count[node] = 1;
+            self.min1[node] = arr[start];
+            self.min_count[node] = 1;
+            return;
+        }
+
+        let mid = (start + end) / 2;
+        self.build(arr, start, mid, left_child(node));
+        self.build(arr, mid + 1, end, right_child(node));
+        self.pull(node);
+    }
+
+    // recompute the summary of a node from its children
+    fn pull(&mut self, node: usize) {
+        let (left, right) = (left_child(node), right_child(node));
+
+        self.sum[node] = self.sum[left] + self.sum[right];
+
+        // maximum, its count and the strict second maximum
+        if self.max1[left] == self.max1[right] {
+            self.max1[node] = self.max1[left];
+            self.max_count[node] = self.max_count[left] + self.max_count[right];
+            self.max2[node] = self.max2[left].max(self.max2[right]);
+        } else if self.max1[left] > self.max1[right] {
+            self.max1[node] = self.max1[left];
+            self.max_count[node] = self.max_count[left];
+            self.max2[node] = self.max2[left].max(self.max1[right]);
+        } else {
+            self.max1[node] = self.max1[right];
+            self.max_count[node] = self.max_count[right];
+            self.max2[node] = self.max1[left].max(self.max2[right]);
+        }
+
+        // minimum, its count and the strict second minimum
+        if self.min1[left] == self.min1[right] {
+            self.min1[node] = self.min1[left];
+            self.min_count[node] = self.min_count[left] + self.min_count[right];
+            self.min2[node] = self.min2[left].min(self.min2[right]);
+        } else if self.min1[left] < self.min1[right] {
+            self.min1[node] = self.min1[left];
+            self.min_count[node] = self.min_count[left];
+            self.min2[node] = self.min2[left].min(self.min1[right]);
+        } else {
+            self.min1[node] = self.min1[right];
+            self.min_count[node] = self.min_count[right];
+            self.min2[node] = self.min1[left].min(self.min2[right]);
+        }
+    }
+
+    // --------------------- TAGS ---------------------
+
+    // add `delta` to every element of the node covering `len` elements
+    fn apply_add(&mut self, node: usize, len: usize, delta: i64) {
+        self.sum[node] += delta * len as i64;
+        self.max1[node] += delta;
+        self.min1[node] += delta;
+        if self.max2[node] != NEG_INF {
+            self.max2[node] += delta;
+        }
+        if self.min2[node] != INF {
+            self.min2[node] += delta;
+        }
+        self.lazy_add[node] += delta;
+    }
+
+    // lower the maximum of the node to `bound`, assuming max2 < bound < max1: only the maximums change
+    fn apply_chmin(&mut self, node: usize, bound: i64) {
+        if bound >= self.max1[node] {
+            return;
+        }
+
+        self.sum[node] -= (self.max1[node] - bound) * self.max_count[node] as i64;
+
+        // the maximum may also be the minimum or the second minimum
+        if self.min1[node] == self.max1[node] {
+            self.min1[node] = bound;
+        } else if self.min2[node] == self.max1[node] {
+            self.min2[node] = bound;
+        }
+        self.max1[node] = bound;
+    }
+
+    // raise the minimum of the node to `bound`, assuming min1 < bound < min2: only the minimums change
+    fn apply_chmax(&mut self, node: usize, bound: i64) {
+        if bound <= self.min1[node] {
+            return;
+        }
+
+        self.sum[node] += (bound - self.min1[node]) * self.min_count[node] as i64;
+
+        if self.max1[node] == self.min1[node] {
+            self.max1[node] = bound;
+        } else if self.max2[node] == self.min1[node] {
+            self.max2[node] = bound;
+        }
+        self.min1[node] = bound;
+    }
+
+    // push the pending updates of a node down to its children: first the addition,
+    // then the chmin/chmax implied by the node's own maximum and minimum
+    fn push_down(&mut self, node: usize, start: usize, end: usize) {
+        let mid = (start + end) / 2;
+        let (left, right) = (left_child(node), right_child(node));
+
+        if self.lazy_add[node] != 0 {
+            let delta = self.lazy_add[node];
+            self.apply_add(left, mid - start + 1, delta);
+            self.apply_add(right, end - mid, delta);
+            self.lazy_add[node] = 0;
+        }
+
+        for child in [left, right] {
+            self.apply_chmin(child, self.max1[node]);
+            self.apply_chmax(child, self.min1[node]);
+        }
+    }
+
+    // ---------------------- RANGE UPDATES ----------------------
+
+    // a[i] = min(a[i], bound) for every i in `range`
+    pub fn chmin_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        bound: i64,
+    ) -> Result<(), RangeError> {
+        if let Some((l, r)) = to_closed(range, self.n)? {
+            self.chmin_rec(0, 0, self.n - 1, l, r, bound);
+        }
+        Ok(())
+    }
+
+    fn chmin_rec(&mut self, node: usize, start: usize, end: usize, l: usize, r: usize, bound: i64) {
+        // no overlap, or nothing above the bound
+        if start > r || end < l || self.max1[node] <= bound {
+            return;
+        }
+
+        // total overlap and only the maximums are affected: update the node in place
+        if start >= l && end <= r && self.max2[node] < bound {
+            self.apply_chmin(node, bound);
+            return;
+        }
+
+        // otherwise "break" the node and recurse
+        self.push_down(node, start, end);
+        let mid = (start + end) / 2;
+        self.chmin_rec(left_child(node), start, mid, l, r, bound);
+        self.chmin_rec(right_child(node), mid + 1, end, l, r, bound);
+        self.pull(node);
+    }
+
+    // a[i] = max(a[i], bound) for every i in `range`
+    pub fn chmax_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        bound: i64,
+    ) -> Result<(), RangeError> {
+        if let Some((l, r)) = to_closed(range, self.n)? {
+            self.chmax_rec(0, 0, self.n - 1, l, r, bound);
+        }
+        Ok(())
+    }
+
+    fn chmax_rec(&mut self, node: usize, start: usize, end: usize, l: usize, r: usize, bound: i64) {
+        if start > r || end < l || self.min1[node] >= bound {
+            return;
+        }
+
+        if start >= l && end <= r && self.min2[node] > bound {
+            self.apply_chmax(node, bound);
+            return;
+        }
+
+        self.push_down(node, start, end);
+        let mid = (start + end) / 2;
+        self.chmax_rec(left_child(node), start, mid, l, r, bound);
+        self.chmax_rec(right_child(node), mid + 1, end, l, r, bound);
+        self.pull(node);
+    }
+
+    // a[i] = a[i] + delta for every i in `range`
+    pub fn add_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        delta: i64,
+    ) -> Result<(), RangeError> {
+        if let Some((l, r)) = to_closed(range, self.n)? {
+            self.add_rec(0, 0, self.n - 1, l, r, delta);
+        }
+        Ok(())
+    }
+
+    fn add_rec(&mut self, node: usize, start: usize, end: usize, l: usize, r: usize, delta: i64) {
+        if start > r || end < l {
+            return;
+        }
+
+        if start >= l && end <= r {
+            self.apply_add(node, end - start + 1, delta);
+            return;
+        }
+
+        self.push_down(node, start, end);
+        let mid = (start + end) / 2;
+        self.add_rec(left_child(node), start, mid, l, r, delta);
+        self.add_rec(right_child(node), mid + 1, end, l, r, delta);
+        self.pull(node);
+    }
+
+    // ---------------------- QUERIES ----------------------
+
+    // sum of the values in `range` (0 for an empty range)
+    pub fn sum_query(&mut self, range: impl RangeBounds<usize>) -> Result<i64, RangeError> {
+        Ok(match to_closed(range, self.n)? {
+            Some((l, r)) => self.query_rec(0, 0, self.n - 1, l, r).0,
+            None => 0,
+        })
+    }
+
+    // max of the values in `range` (`i64::MIN` for an empty range)
+    pub fn max_query(&mut self, range: impl RangeBounds<usize>) -> Result<i64, RangeError> {
+        Ok(match to_closed(range, self.n)? {
+            Some((l, r)) => self.query_rec(0, 0, self.n - 1, l, r).1,
+            None => NEG_INF,
+        })
+    }
+
+    // min of the values in `range` (`i64::MAX` for an empty range)
+    pub fn min_query(&mut self, range: impl RangeBounds<usize>) -> Result<i64, RangeError> {
+        Ok(match to_closed(range, self.n)? {
+            Some((l, r)) => self.query_rec(0, 0, self.n - 1, l, r).2,
+            None => INF,
+        })
+    }
+
+    // returns the (sum, max, min) of the values in range [l, r]
+    fn query_rec(
+        &mut self,
+        node: usize,
+        start: usize,
+        end: usize,
+        l: usize,
+        r: usize,
+    ) -> (i64, i64, i64) {
+        if start > r || end < l {
+            return (0, NEG_INF, INF);
+        }
+
+        if start >= l && end <= r {
+            return (self.sum[node], self.max1[node], self.min1[node]);
+        }
+
+        self.push_down(node, start, end);
+        let mid = (start + end) / 2;
+        let left = self.query_rec(left_child(node), start, mid, l, r);
+        let right = self.query_rec(right_child(node), mid + 1, end, l, r);
+        (left.0 + right.0, left.1.max(right.1), left.2.min(right.2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    #[test]
+    fn test_beats_against_brute_force() {
+        let mut a: Vec<i64> = vec![5, -3, 8, 0, 12, -7, 4, 4, 9, 1, -2, 6];
+        let mut beats = SegmentTreeBeats::init(&a);
+
+        let mut rng = Lcg(3);
+
+        for _ in 0..3000 {
+            let x = rng.next(a.len());
+            let y = rng.next(a.len());
+            let range = x.min(y)..=x.max(y);
+            let value = rng.next(31) as i64 - 15;
+
+            match rng.next(6) {
+                0 => {
+                    beats.chmin_range(range.clone(), value).unwrap();
+                    a[range].iter_mut().for_each(|v| *v = (*v).min(value));
+                }
+                1 => {
+                    beats.chmax_range(range.clone(), value).unwrap();
+                    a[range].iter_mut().for_each(|v| *v = (*v).max(value));
+                }
+                2 => {
+                    beats.add_range(range.clone(), value).unwrap();
+                    a[range].iter_mut().for_each(|v| *v += value);
+                }
+                3 => assert_eq!(beats.sum_query(range.clone()), Ok(a[range].iter().sum())),
+                4 => assert_eq!(
+                    beats.max_query(range.clone()).ok(),
+                    a[range].iter().max().copied()
+                ),
+                _ => assert_eq!(
+                    beats.min_query(range.clone()).ok(),
+                    a[range].iter().min().copied()
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_beats_chmin_then_sum() {
+        let mut beats = SegmentTreeBeats::init(&[1, 6, 3, 9, 2]);
+
+        beats.chmin_range(.., 4).unwrap();
+        assert_eq!(beats.sum_query(..), Ok(1 + 4 + 3 + 4 + 2));
+        assert_eq!(beats.max_query(..), Ok(4));
+
+        beats.chmax_range(1..=3, 5).unwrap();
+        assert_eq!(beats.sum_query(..), Ok(1 + 5 + 5 + 5 + 2));
+        assert_eq!(beats.min_query(1..=3), Ok(5));
+
+        beats.add_range(..3, -10).unwrap();
+        assert_eq!(beats.sum_query(..), Ok(-9 - 5 - 5 + 5 + 2));
+        assert_eq!(beats.min_query(..), Ok(-9));
+        assert_eq!(
+            beats.sum_query(5..6),
+            Err(RangeError::OutOfBounds { end: 6, len: 5 })
+        );
+
+        let mut empty = SegmentTreeBeats::init(&[]);
+        assert_eq!(empty.chmin_range(.., 4), Ok(()));
+        assert_eq!(empty.sum_query(..), Ok(0));
+        assert_eq!(empty.max_query(..), Ok(i64::MIN));
+        assert_eq!(
+            empty.sum_query(..1),
+            Err(RangeError::OutOfBounds { end: 1, len: 0 })
+        );
+    }
+}
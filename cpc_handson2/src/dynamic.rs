@@ -0,0 +1,444 @@
+use std::ops::{Range, RangeBounds};
+
+use crate::lazy::{LazyAction, NoAction, RangeAdd, RangeAssign};
+use crate::monoid::{power, Monoid};
+use crate::range::{check_index, to_closed, RangeError};
+
+struct Node<V, T> {
+    value: V,
+    lazy: Option<T>,                  // pending for the children, as in `SegmentTree`
+    children: Option<(usize, usize)>, // created on the first update that goes below the node
+}
+
+/// Segment tree over a huge range of positions (up to `usize::MAX`, e.g. a `0..10^18` timeline),
+/// where only the nodes touched by an update are allocated, in an arena: each update creates
+/// O(log len) nodes, and the memory does not depend on `len`.
+///
+/// Every position starts with the same `fill` value, so a range that no update went through
+/// is summarized by combining `fill` with itself (see `monoid::power`). Reads never allocate:
+/// they carry the pending tags down like `SegmentTree`, and stop at untouched nodes.
+pub struct DynamicSegmentTree<M: Monoid, A: LazyAction<M> = NoAction> {
+    len: usize,
+    fill: M::Value,
+    nodes: Vec<Node<M::Value, A::Tag>>,
+}
+
+impl<M: Monoid, A: LazyAction<M>> DynamicSegmentTree<M, A> {
+    // --------------------- CONSTRUCTOR ---------------------
+
+    /// Tree over `len` positions holding the identity.
+    pub fn new(len: usize) -> Self {
+        Self::with_fill(len, M::identity())
+    }
+
+    /// Tree over `len` positions all holding `fill`.
+    pub fn with_fill(len: usize, fill: M::Value) -> Self {
+        let mut tree = Self {
+            len,
+            fill,
+            nodes: Vec::new(),
+        };
+        tree.new_node(len);
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of allocated nodes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // --------------------- QUERY ---------------------
+
+    /// Combination of the values in `range` (the identity for an empty range).
+    pub fn query(&self, range: impl RangeBounds<usize>) -> Result<M::Value, RangeError> {
+        Ok(match to_closed(range, self.len)? {
+            Some((l, r)) => self.query_rec(0, 0, self.len - 1, l, r, &None),
+            None => M::identity(),
+        })
+    }
+
+    fn query_rec(
+        &self,
+        node: usize,
+        start: usize,
+        end: usize,
+        l: usize,
+        r: usize,
+        pending: &Option<A::Tag>,
+    ) -> M::Value {
+        if start > r || end < l {
+            // no overlap
+            return M::identity();
+        }
+
+        if start >= l && end <= r {
+            // total overlap
+            return self.value_with(self.nodes[node].value.clone(), end - start + 1, pending);
+        }
+
+        let pending = self.pending_for_children(node, pending);
+        match self.nodes[node].children {
+            Some((left, right)) => {
+                let mid = start + (end - start) / 2;
+                M::combine(
+                    &self.query_rec(left, start, mid, l, r, &pending),
+                    &self.query_rec(right, mid + 1, end, l, r, &pending),
+                )
+            }
+            None => {
+                // untouched below: every position of the overlap holds `fill` with the pending tags applied
+                let overlap = end.min(r) - start.max(l) + 1;
+                self.value_with(power::<M>(&self.fill, overlap), overlap, &pending)
+            }
+        }
+    }
+
+    /// Current value of `a[i]`.
+    pub fn get(&self, i: usize) -> Result<M::Value, RangeError> {
+        check_index(i, self.len)?;
+        self.query(i..=i)
+    }
+
+    // ---------------------- UPDATE ----------------------
+
+    /// Applies the update described by `tag` to every position in `range`.
+    pub fn apply_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        tag: A::Tag,
+    ) -> Result<(), RangeError> {
+        if let Some((l, r)) = to_closed(range, self.len)? {
+            self.apply_range_rec(0, 0, self.len - 1, l, r, &tag);
+        }
+        Ok(())
+    }
+
+    fn apply_range_rec(
+        &mut self,
+        node: usize,
+        start: usize,
+        end: usize,
+        l: usize,
+        r: usize,
+        tag: &A::Tag,
+    ) {
+        if start > r || end < l {
+            // no overlap
+            return;
+        }
+
+        if start >= l && end <= r {
+            // total overlap, leave the update pending for the children
+            self.apply_tag(node, end - start + 1, tag);
+            return;
+        }
+
+        let (left, right) = self.push_down(node, start, end);
+        let mid = start + (end - start) / 2;
+        self.apply_range_rec(left, start, mid, l, r, tag);
+        self.apply_range_rec(right, mid + 1, end, l, r, tag);
+        self.pull(node, left, right);
+    }
+
+    /// `a[i] = value`, any pending update on position `i` is overwritten.
+    pub fn set(&mut self, i: usize, value: M::Value) -> Result<(), RangeError> {
+        check_index(i, self.len)?;
+
+        // walk down to the leaf, creating the nodes on the way, then fix the path bottom-up
+        let mut path = Vec::new();
+        let (mut node, mut start, mut end) = (0, 0, self.len - 1);
+        while start != end {
+            let (left, right) = self.push_down(node, start, end);
+            path.push((node, left, right));
+
+            let mid = start + (end - start) / 2;
+            if i <= mid {
+                node = left;
+                end = mid;
+            } else {
+                node = right;
+                start = mid + 1;
+            }
+        }
+
+        self.nodes[node].value = value;
+        for &(node, left, right) in path.iter().rev() {
+            self.pull(node, left, right);
+        }
+        Ok(())
+    }
+
+    // --------------------- HELPERS ---------------------
+
+    // new untouched node over `len` positions
+    fn new_node(&mut self, len: usize) -> usize {
+        self.nodes.push(Node {
+            value: power::<M>(&self.fill, len),
+            lazy: None,
+            children: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    fn pull(&mut self, node: usize, left: usize, right: usize) {
+        self.nodes[node].value = M::combine(&self.nodes[left].value, &self.nodes[right].value);
+    }
+
+    // apply a tag to a node over `len` positions, and remember it for the children if it has some
+    // (or may get some later)
+    fn apply_tag(&mut self, node: usize, len: usize, tag: &A::Tag) {
+        let node = &mut self.nodes[node];
+        node.value = A::apply(tag, &node.value, len);
+
+        if len > 1 {
+            node.lazy = Some(match &node.lazy {
+                Some(old_tag) => A::compose(tag, old_tag),
+                None => tag.clone(),
+            });
+        }
+    }
+
+    // create the children of a node if needed, push its pending tag down to them, and return them
+    fn push_down(&mut self, node: usize, start: usize, end: usize) -> (usize, usize) {
+        let mid = start + (end - start) / 2;
+        let (left, right) = match self.nodes[node].children {
+            Some(children) => children,
+            None => {
+                let children = (self.new_node(mid - start + 1), self.new_node(end - mid));
+                self.nodes[node].children = Some(children);
+                children
+            }
+        };
+
+        if let Some(tag) = self.nodes[node].lazy.take() {
+            self.apply_tag(left, mid - start + 1, &tag);
+            self.apply_tag(right, end - mid, &tag);
+        }
+        (left, right)
+    }
+
+    fn value_with(&self, value: M::Value, len: usize, pending: &Option<A::Tag>) -> M::Value {
+        match pending {
+            Some(tag) => A::apply(tag, &value, len),
+            None => value,
+        }
+    }
+
+    fn pending_for_children(&self, node: usize, pending: &Option<A::Tag>) -> Option<A::Tag> {
+        match (pending, &self.nodes[node].lazy) {
+            (Some(outer), Some(inner)) => Some(A::compose(outer, inner)),
+            (Some(tag), None) | (None, Some(tag)) => Some(tag.clone()),
+            (None, None) => None,
+        }
+    }
+}
+
+impl<M: Monoid, A: RangeAdd<M>> DynamicSegmentTree<M, A> {
+    /// `a[i] = a[i] + delta` for every `i` in `range`.
+    pub fn add_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        delta: M::Value,
+    ) -> Result<(), RangeError> {
+        self.apply_range(range, A::add_tag(delta))
+    }
+}
+
+impl<M: Monoid, A: RangeAssign<M>> DynamicSegmentTree<M, A> {
+    /// `a[i] = value` for every `i` in `range`.
+    pub fn assign_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        value: M::Value,
+    ) -> Result<(), RangeError> {
+        self.apply_range(range, A::assign_tag(value))
+    }
+}
+
+/// Maps arbitrary coordinates to dense indices, so that a `SegmentTree` sized by the number of
+/// distinct endpoints can stand in for a huge range of positions.
+///
+/// The sorted distinct points `p_0 < p_1 < ...` cut the line into elementary intervals, index `i`
+/// standing for `[p_i, p_(i+1))`. Any half-open interval whose two ends are among the points is then
+/// a contiguous range of indices.
+///
+/// The points are kept as `u128`, so that the end `r + 1` of a closed segment reaching `u64::MAX`
+/// is the point `2^64` rather than an overflow.
+#[derive(Debug, Clone)]
+pub struct CoordinateCompression {
+    points: Vec<u128>,
+}
+
+impl CoordinateCompression {
+    pub fn new(points: impl IntoIterator<Item = u64>) -> Self {
+        Self::from_points(points.into_iter().map(u128::from))
+    }
+
+    /// Compression of the endpoints of closed segments `[l, r]`: both `l` and `r + 1` are kept,
+    /// so that each segment maps to whole elementary intervals.
+    pub fn from_segments(segments: &[(u64, u64)]) -> Self {
+        Self::from_points(
+            segments
+                .iter()
+                .flat_map(|&(l, r)| [u128::from(l), u128::from(r) + 1]),
+        )
+    }
+
+    fn from_points(points: impl Iterator<Item = u128>) -> Self {
+        let mut points: Vec<u128> = points.collect();
+        points.sort_unstable();
+        points.dedup();
+        Self { points }
+    }
+
+    /// The sorted distinct points (the last one may be `2^64`, see `from_segments`).
+    pub fn points(&self) -> &[u128] {
+        &self.points
+    }
+
+    /// Number of elementary intervals, i.e. the length of the array to build the tree on.
+    pub fn interval_count(&self) -> usize {
+        self.points.len().saturating_sub(1)
+    }
+
+    /// Index of the point `x`, if it is one of the points.
+    pub fn index(&self, x: u64) -> Option<usize> {
+        self.points.binary_search(&u128::from(x)).ok()
+    }
+
+    /// Index of the elementary interval that contains `x`, if any.
+    pub fn interval_of(&self, x: u64) -> Option<usize> {
+        let after = self.points.partition_point(|&p| p <= u128::from(x));
+        (after > 0 && after < self.points.len()).then(|| after - 1)
+    }
+
+    /// Indices of the elementary intervals making up the closed segment `[l, r]`,
+    /// or `None` if `l` or `r + 1` is not one of the points.
+    pub fn segment(&self, l: u64, r: u64) -> Option<Range<usize>> {
+        let end = self.points.binary_search(&(u128::from(r) + 1)).ok()?;
+        Some(self.index(l)?..end)
+    }
+
+    /// Length of the elementary interval `i` (up to `2^64`, for `[0, u64::MAX]`).
+    pub fn interval_len(&self, i: usize) -> u128 {
+        self.points[i + 1] - self.points[i]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lazy::{Add, AddAssign};
+    use crate::monoid::{MinMax, Sum};
+    use crate::test_util::Lcg;
+    use crate::SegmentTree;
+
+    #[test]
+    fn test_dynamic_tree_against_segment_tree() {
+        let n = 37;
+        let mut dynamic = DynamicSegmentTree::<Sum, AddAssign>::with_fill(n, 3);
+        let mut tree = SegmentTree::<Sum, AddAssign>::init(&vec![3; n]);
+
+        let mut rng = Lcg(8);
+        for _ in 0..1000 {
+            let (x, y) = (rng.next(n + 1), rng.next(n + 1));
+            let range = x.min(y)..x.max(y);
+            let v = rng.next(21) as i64 - 10;
+
+            match rng.next(4) {
+                0 => {
+                    dynamic.add_range(range.clone(), v).unwrap();
+                    tree.add_range(range, v).unwrap();
+                }
+                1 => {
+                    dynamic.assign_range(range.clone(), v).unwrap();
+                    tree.assign_range(range, v).unwrap();
+                }
+                2 => {
+                    let i = rng.next(n);
+                    dynamic.set(i, v).unwrap();
+                    tree.set(i, v).unwrap();
+                    assert_eq!(dynamic.get(i), tree.get(i));
+                }
+                _ => assert_eq!(dynamic.query(range.clone()), tree.query(range)),
+            }
+        }
+
+        assert_eq!(
+            dynamic.query(..=n),
+            Err(RangeError::OutOfBounds { end: n + 1, len: n })
+        );
+    }
+
+    #[test]
+    fn test_huge_timeline() {
+        const LEN: usize = 1_000_000_000_000_000_000;
+        let segments: [(u64, u64); 4] = [
+            (0, 999_999_999_999),
+            (500_000_000_000, 10u64.pow(17)),
+            (10u64.pow(17), 10u64.pow(18) - 1),
+            (42, 42),
+        ];
+
+        // coverage of each position, directly on the timeline
+        let mut dynamic = DynamicSegmentTree::<MinMax<i32>, Add<i32>>::with_fill(LEN, (0, 0));
+        for &(l, r) in &segments {
+            dynamic.apply_range(l as usize..=r as usize, 1).unwrap();
+        }
+        assert!(dynamic.node_count() < 1000);
+        assert_eq!(dynamic.query(..), Ok((1, 2)));
+        assert_eq!(dynamic.get(42), Ok((2, 2)));
+        assert_eq!(dynamic.get(10usize.pow(17)), Ok((2, 2)));
+        assert_eq!(dynamic.query(10usize.pow(17) + 1..), Ok((1, 1)));
+
+        // the same, on a dense tree over the compressed endpoints
+        let compression = CoordinateCompression::from_segments(&segments);
+        let mut coverage = SegmentTree::coverage(compression.interval_count());
+        for &(l, r) in &segments {
+            coverage
+                .add_segment(compression.segment(l, r).unwrap())
+                .unwrap();
+        }
+
+        for x in [
+            0,
+            41,
+            42,
+            43,
+            500_000_000_000,
+            10u64.pow(17),
+            10u64.pow(18) - 1,
+        ] {
+            let i = compression.interval_of(x).unwrap();
+            assert_eq!(coverage.get(i), dynamic.get(x as usize));
+        }
+        assert_eq!(compression.interval_of(10u64.pow(18)), None);
+        assert_eq!(
+            compression.interval_len(compression.interval_of(42).unwrap()),
+            1
+        );
+        assert_eq!(compression.segment(1, 2), None);
+
+        // a segment reaching u64::MAX ends at the point 2^64
+        let compression = CoordinateCompression::from_segments(&[(5, u64::MAX), (0, 9)]);
+        assert_eq!(compression.points(), &[0, 5, 10, 1 << 64]);
+        assert_eq!(compression.segment(0, 9), Some(0..2));
+        assert_eq!(
+            compression.segment(5, u64::MAX),
+            Some(1..compression.interval_count())
+        );
+        assert_eq!(compression.interval_of(u64::MAX), Some(2));
+        assert_eq!(compression.interval_len(2), (1 << 64) - 10);
+        let whole = CoordinateCompression::from_segments(&[(0, u64::MAX)]);
+        assert_eq!(whole.segment(0, u64::MAX), Some(0..1));
+        assert_eq!(whole.interval_len(0), 1 << 64);
+    }
+}
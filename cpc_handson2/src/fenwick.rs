@@ -0,0 +1,397 @@
+use std::ops::RangeBounds;
+
+use crate::num::Number;
+use crate::range::{check_index, to_half_open, RangeError};
+
+/// Fenwick tree (binary indexed tree): point additions and prefix sums in O(log n),
+/// with `n` values of memory. Lighter than a `SegmentTree<Sum>` when only sums are needed.
+///
+/// Internally 1-based: `tree[i]` holds the sum of the `i & i.wrapping_neg()` positions ending at `i`.
+#[derive(Debug, Clone)]
+pub struct Fenwick<T = i64> {
+    tree: Vec<T>,
+}
+
+impl<T: Number> Fenwick<T> {
+    /// Fenwick tree over `n` zeros.
+    pub fn new(n: usize) -> Self {
+        Self {
+            tree: vec![T::zero(); n + 1],
+        }
+    }
+
+    /// Fenwick tree over the values of `a`, built in O(n).
+    pub fn from_slice(a: &[T]) -> Self {
+        let mut tree = vec![T::zero(); a.len() + 1];
+        tree[1..].copy_from_slice(a);
+
+        // each node gives its partial sum to the next node covering it
+        for i in 1..tree.len() {
+            let parent = i + (i & i.wrapping_neg());
+            if parent < tree.len() {
+                tree[parent] = tree[parent] + tree[i];
+            }
+        }
+
+        Self { tree }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `a[i] = a[i] + delta`.
+    pub fn add(&mut self, i: usize, delta: T) -> Result<(), RangeError> {
+        check_index(i, self.len())?;
+
+        let mut node = i + 1;
+        while node < self.tree.len() {
+            self.tree[node] = self.tree[node] + delta;
+            node += node & node.wrapping_neg();
+        }
+        Ok(())
+    }
+
+    /// Sum of `a[..end]`.
+    pub fn prefix_sum(&self, end: usize) -> Result<T, RangeError> {
+        let (_, end) = to_half_open(..end, self.len())?;
+
+        let mut sum = T::zero();
+        let mut node = end;
+        while node > 0 {
+            sum = sum + self.tree[node];
+            node -= node & node.wrapping_neg();
+        }
+        Ok(sum)
+    }
+
+    /// Sum of the values in `range`.
+    pub fn sum(&self, range: impl RangeBounds<usize>) -> Result<T, RangeError> {
+        let (start, end) = to_half_open(range, self.len())?;
+        Ok(self.prefix_sum(end)? - self.prefix_sum(start)?)
+    }
+}
+
+impl<T: Number + PartialOrd> Fenwick<T> {
+    /// Smallest `end` such that the sum of `a[..end]` is at least `target`, or `None` if even the
+    /// whole array sums to less. The values must be non-negative, so that the prefix sums only grow.
+    pub fn lower_bound(&self, target: T) -> Option<usize> {
+        if target <= T::zero() {
+            return Some(0);
+        }
+
+        // walk down the implicit tree: take a block whenever the target is still out of reach
+        let mut position = 0;
+        let mut remaining = target;
+        let mut step = self.len().next_power_of_two();
+        while step > 0 {
+            let next = position + step;
+            if next < self.tree.len() && self.tree[next] < remaining {
+                position = next;
+                remaining = remaining - self.tree[next];
+            }
+            step >>= 1;
+        }
+
+        // `position` is the longest prefix summing to less than the target
+        (position < self.len()).then_some(position + 1)
+    }
+}
+
+/// Fenwick tree with range additions and range sums, built on two point-update trees:
+/// adding `delta` to `a[l..r]` makes the prefix sum up to `p` grow by `delta * (p - l)` inside the range,
+/// which is kept as `p * slope(p) - offset(p)`. The offsets go negative, so `T` must be a signed type.
+#[derive(Debug, Clone)]
+pub struct RangeFenwick<T = i64> {
+    slope: Fenwick<T>,
+    offset: Fenwick<T>,
+}
+
+impl<T: Number> RangeFenwick<T> {
+    /// Tree over `n` zeros.
+    pub fn new(n: usize) -> Self {
+        // one more position, so that a range can end at `n`
+        Self {
+            slope: Fenwick::new(n + 1),
+            offset: Fenwick::new(n + 1),
+        }
+    }
+
+    /// Tree over the values of `a`.
+    pub fn from_slice(a: &[T]) -> Self {
+        let mut slope = vec![T::zero(); a.len() + 1];
+        let mut offset = vec![T::zero(); a.len() + 1];
+
+        // a[i] is a range addition on [i, i + 1)
+        for (i, &value) in a.iter().enumerate() {
+            slope[i] = slope[i] + value;
+            slope[i + 1] = slope[i + 1] - value;
+            offset[i] = offset[i] + value * T::from_usize(i);
+            offset[i + 1] = offset[i + 1] - value * T::from_usize(i + 1);
+        }
+
+        Self {
+            slope: Fenwick::from_slice(&slope),
+            offset: Fenwick::from_slice(&offset),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slope.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// `a[i] = a[i] + delta` for every `i` in `range`.
+    pub fn add_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        delta: T,
+    ) -> Result<(), RangeError> {
+        let (start, end) = to_half_open(range, self.len())?;
+        if start == end {
+            return Ok(());
+        }
+
+        let zero = T::zero();
+        self.slope.add(start, delta)?;
+        self.slope.add(end, zero - delta)?;
+        self.offset.add(start, delta * T::from_usize(start))?;
+        self.offset.add(end, zero - delta * T::from_usize(end))?;
+        Ok(())
+    }
+
+    /// Sum of `a[..end]`.
+    pub fn prefix_sum(&self, end: usize) -> Result<T, RangeError> {
+        let (_, end) = to_half_open(..end, self.len())?;
+        Ok(self.slope.prefix_sum(end)? * T::from_usize(end) - self.offset.prefix_sum(end)?)
+    }
+
+    /// Sum of the values in `range`.
+    pub fn sum(&self, range: impl RangeBounds<usize>) -> Result<T, RangeError> {
+        let (start, end) = to_half_open(range, self.len())?;
+        Ok(self.prefix_sum(end)? - self.prefix_sum(start)?)
+    }
+
+    /// Current value of `a[i]`.
+    pub fn get(&self, i: usize) -> Result<T, RangeError> {
+        check_index(i, self.len())?;
+        self.sum(i..=i)
+    }
+}
+
+/// Two-dimensional Fenwick tree over a `rows x cols` grid: point additions and rectangle sums in O(log² n).
+#[derive(Debug, Clone)]
+pub struct Fenwick2D<T = i64> {
+    rows: usize,
+    cols: usize,
+    tree: Vec<T>, // (rows + 1) x (cols + 1), row-major, 1-based like `Fenwick`
+}
+
+impl<T: Number> Fenwick2D<T> {
+    /// Grid of zeros.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            tree: vec![T::zero(); (rows + 1) * (cols + 1)],
+        }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// `a[row][col] = a[row][col] + delta`.
+    pub fn add(&mut self, row: usize, col: usize, delta: T) -> Result<(), RangeError> {
+        check_index(row, self.rows)?;
+        check_index(col, self.cols)?;
+
+        let mut i = row + 1;
+        while i <= self.rows {
+            let mut j = col + 1;
+            while j <= self.cols {
+                let cell = i * (self.cols + 1) + j;
+                self.tree[cell] = self.tree[cell] + delta;
+                j += j & j.wrapping_neg();
+            }
+            i += i & i.wrapping_neg();
+        }
+        Ok(())
+    }
+
+    /// Sum of the rectangle `a[..rows_end][..cols_end]`.
+    pub fn prefix_sum(&self, rows_end: usize, cols_end: usize) -> Result<T, RangeError> {
+        let (_, rows_end) = to_half_open(..rows_end, self.rows)?;
+        let (_, cols_end) = to_half_open(..cols_end, self.cols)?;
+
+        let mut sum = T::zero();
+        let mut i = rows_end;
+        while i > 0 {
+            let mut j = cols_end;
+            while j > 0 {
+                sum = sum + self.tree[i * (self.cols + 1) + j];
+                j -= j & j.wrapping_neg();
+            }
+            i -= i & i.wrapping_neg();
+        }
+        Ok(sum)
+    }
+
+    /// Sum of the rectangle made of the rows in `rows` and the columns in `cols`.
+    pub fn sum(
+        &self,
+        rows: impl RangeBounds<usize>,
+        cols: impl RangeBounds<usize>,
+    ) -> Result<T, RangeError> {
+        let (top, bottom) = to_half_open(rows, self.rows)?;
+        let (left, right) = to_half_open(cols, self.cols)?;
+
+        // inclusion-exclusion on the four prefix rectangles, grouped so that the subtraction never
+        // goes below zero with unsigned values
+        let outer = self.prefix_sum(bottom, right)? + self.prefix_sum(top, left)?;
+        let strips = self.prefix_sum(top, right)? + self.prefix_sum(bottom, left)?;
+        Ok(outer - strips)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lazy::Add;
+    use crate::monoid::Sum;
+    use crate::test_util::Lcg;
+    use crate::SegmentTree;
+
+    #[test]
+    fn test_fenwick_against_segment_tree() {
+        let n = 40;
+        let a: Vec<i64> = (0..n as i64).map(|i| (i * 13) % 17 - 8).collect();
+        let mut fenwick = Fenwick::from_slice(&a);
+        let mut range_fenwick = RangeFenwick::from_slice(&a);
+        let mut point_tree = SegmentTree::<Sum, Add>::init(&a);
+        let mut range_tree = SegmentTree::<Sum, Add>::init(&a);
+
+        let mut rng = Lcg(5);
+        for _ in 0..2000 {
+            let x = rng.next(n + 1);
+            let y = rng.next(n + 1);
+            let range = x.min(y)..x.max(y);
+            let delta = rng.next(21) as i64 - 10;
+
+            match rng.next(3) {
+                0 => {
+                    let i = rng.next(n);
+                    fenwick.add(i, delta).unwrap();
+                    let value = point_tree.get(i).unwrap();
+                    point_tree.set(i, value + delta).unwrap();
+                }
+                1 => {
+                    range_fenwick.add_range(range.clone(), delta).unwrap();
+                    range_tree.add_range(range, delta).unwrap();
+                }
+                _ => {
+                    assert_eq!(fenwick.sum(range.clone()), point_tree.query(range.clone()));
+                    assert_eq!(
+                        range_fenwick.sum(range.clone()),
+                        range_tree.query(range.clone())
+                    );
+                    assert_eq!(fenwick.prefix_sum(range.end), point_tree.query(..range.end));
+                }
+            }
+        }
+
+        for i in 0..n {
+            assert_eq!(range_fenwick.get(i), range_tree.get(i));
+        }
+        assert_eq!(
+            fenwick.add(n, 1),
+            Err(RangeError::IndexOutOfBounds { index: n, len: n })
+        );
+        assert_eq!(
+            range_fenwick.sum(..=n),
+            Err(RangeError::OutOfBounds { end: n + 1, len: n })
+        );
+    }
+
+    #[test]
+    fn test_lower_bound() {
+        let a: Vec<u64> = vec![2, 0, 3, 1, 0, 0, 4];
+        let fenwick = Fenwick::from_slice(&a);
+
+        // prefix sums: 0, 2, 2, 5, 6, 6, 6, 10
+        assert_eq!(fenwick.lower_bound(0), Some(0));
+        assert_eq!(fenwick.lower_bound(1), Some(1));
+        assert_eq!(fenwick.lower_bound(2), Some(1));
+        assert_eq!(fenwick.lower_bound(3), Some(3));
+        assert_eq!(fenwick.lower_bound(6), Some(4));
+        assert_eq!(fenwick.lower_bound(7), Some(7));
+        assert_eq!(fenwick.lower_bound(10), Some(7));
+        assert_eq!(fenwick.lower_bound(11), None);
+
+        let mut rng = Lcg(9);
+        let mut fenwick = Fenwick::<u64>::new(33);
+        let mut values = vec![0u64; 33];
+        for _ in 0..500 {
+            let i = rng.next(33);
+            let delta = rng.next(5) as u64;
+            fenwick.add(i, delta).unwrap();
+            values[i] += delta;
+
+            let target = rng.next(80) as u64;
+            let expected =
+                (0..=values.len()).find(|&end| values[..end].iter().sum::<u64>() >= target);
+            assert_eq!(fenwick.lower_bound(target), expected);
+        }
+    }
+
+    #[test]
+    fn test_fenwick_2d() {
+        let (rows, cols) = (7, 9);
+        let mut fenwick = Fenwick2D::new(rows, cols);
+        let mut grid = vec![vec![0i64; cols]; rows];
+
+        let mut rng = Lcg(3);
+        for _ in 0..300 {
+            let (row, col) = (rng.next(rows), rng.next(cols));
+            let delta = rng.next(11) as i64 - 5;
+            fenwick.add(row, col, delta).unwrap();
+            grid[row][col] += delta;
+
+            let (a, b) = (rng.next(rows + 1), rng.next(rows + 1));
+            let (c, d) = (rng.next(cols + 1), rng.next(cols + 1));
+            let (row_range, col_range) = (a.min(b)..a.max(b), c.min(d)..c.max(d));
+            let expected: i64 = grid[row_range.clone()]
+                .iter()
+                .map(|line| line[col_range.clone()].iter().sum::<i64>())
+                .sum();
+            assert_eq!(fenwick.sum(row_range, col_range), Ok(expected));
+        }
+
+        assert_eq!(
+            fenwick.add(0, cols, 1),
+            Err(RangeError::IndexOutOfBounds {
+                index: cols,
+                len: cols
+            })
+        );
+
+        // unsigned values: no intermediate result may go below zero
+        let mut unsigned = Fenwick2D::<u64>::new(2, 2);
+        unsigned.add(0, 0, 5).unwrap();
+        unsigned.add(1, 1, 3).unwrap();
+        assert_eq!(unsigned.sum(1..2, 1..2), Ok(3));
+        assert_eq!(unsigned.sum(0..1, 1..2), Ok(0));
+        assert_eq!(unsigned.sum(.., ..), Ok(8));
+    }
+}
@@ -0,0 +1,585 @@
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+
+use crate::lazy::{ChMin, LazyAction, NoAction, RangeAdd, RangeAssign};
+use crate::monoid::{Max, Monoid};
+use crate::num::Bounded;
+use crate::range::{check_index, to_half_open, RangeError};
+
+/// Non-recursive lazy segment tree, with the same generic API as `SegmentTree`: `query`, `get`,
+/// `to_vec`, `max_right`, `min_left` and `find_first` take `&self`, and `apply_range`, `set`,
+/// `add_range`, `assign_range`, `update_range` (chmin) and `max_query` behave the same way.
+/// The problem-specific coverage methods of `SegmentTree` (`exists_exact_coverage`, the coverage
+/// and coverage-count trees) are not provided.
+///
+/// The array is padded to the next power of two `size`: node 1 is the root, the children of node `k`
+/// are `2k` and `2k + 1`, and the leaves are `size..size + n`. This takes `2 * size` values and
+/// `size` lazy tags (at most `4n` and `2n`), against `4n` of each for the recursive tree.
+/// Updates and queries walk the two borders of the range from the leaves to the root. The updates
+/// push the pending tags down the borders first, while the reads compose the tags of the border
+/// paths and apply them on the fly, so that a built tree can be shared between threads.
+pub struct IterativeSegmentTree<M: Monoid, A: LazyAction<M> = NoAction> {
+    n: usize,
+    size: usize,
+    log: u32,
+    tree: Vec<M::Value>,
+    lazy: Vec<Option<A::Tag>>, // only the inner nodes 1..size have a tag
+    action: PhantomData<A>,
+}
+
+impl<M: Monoid, A: LazyAction<M>> IterativeSegmentTree<M, A> {
+    // --------------------- CONSTRUCTOR ---------------------
+
+    /// Builds the tree over the array `a`.
+    pub fn init(a: &[M::Value]) -> Self {
+        let size = a.len().next_power_of_two();
+
+        let mut tree = vec![M::identity(); 2 * size];
+        tree[size..size + a.len()].clone_from_slice(a);
+
+        let mut segment_tree = Self {
+            n: a.len(),
+            size,
+            log: size.trailing_zeros(),
+            tree,
+            lazy: vec![None; size],
+            action: PhantomData,
+        };
+        for k in (1..size).rev() {
+            segment_tree.pull(k);
+        }
+        segment_tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    // --------------------- QUERY ---------------------
+
+    /// Combination of the values in `range` (the identity for an empty range).
+    pub fn query(&self, range: impl RangeBounds<usize>) -> Result<M::Value, RangeError> {
+        let (l, r) = to_half_open(range, self.n)?;
+        if l == r {
+            return Ok(M::identity());
+        }
+
+        // every node gathered below is a child of a node on one of the two border paths
+        let (l, r) = (l + self.size, r + self.size);
+        let left_path = self.path_pending(l);
+        let right_path = self.path_pending(r - 1);
+        let value_of = |k: usize| {
+            let level = self.level_of(k);
+            let pending = if k >> 1 == l >> (level + 1) {
+                &left_path[level + 1]
+            } else {
+                &right_path[level + 1]
+            };
+            self.value_with(k, pending)
+        };
+
+        // values gathered from the left and from the right border
+        let mut left_value = M::identity();
+        let mut right_value = M::identity();
+        let (mut left, mut right) = (l, r);
+        while left < right {
+            if left & 1 == 1 {
+                left_value = M::combine(&left_value, &value_of(left));
+                left += 1;
+            }
+            if right & 1 == 1 {
+                right -= 1;
+                right_value = M::combine(&value_of(right), &right_value);
+            }
+            left >>= 1;
+            right >>= 1;
+        }
+
+        Ok(M::combine(&left_value, &right_value))
+    }
+
+    // ---------------------- RANGE UPDATE ----------------------
+
+    /// Applies the update described by `tag` to every position in `range`.
+    pub fn apply_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        tag: A::Tag,
+    ) -> Result<(), RangeError> {
+        let (l, r) = to_half_open(range, self.n)?;
+        if l == r {
+            return Ok(());
+        }
+
+        let (l, r) = (l + self.size, r + self.size);
+        self.push_borders(l, r);
+
+        // tag the nodes that exactly cover the range
+        let (mut left, mut right) = (l, r);
+        while left < right {
+            if left & 1 == 1 {
+                self.apply_tag(left, &tag);
+                left += 1;
+            }
+            if right & 1 == 1 {
+                right -= 1;
+                self.apply_tag(right, &tag);
+            }
+            left >>= 1;
+            right >>= 1;
+        }
+
+        // recompute the ancestors of the borders, bottom-up
+        for i in 1..=self.log {
+            if (l >> i) << i != l {
+                self.pull(l >> i);
+            }
+            if (r >> i) << i != r {
+                self.pull((r - 1) >> i);
+            }
+        }
+
+        Ok(())
+    }
+
+    // ---------------------- POINT ACCESS ----------------------
+
+    /// `a[i] = value`, any pending update on position `i` is overwritten.
+    pub fn set(&mut self, i: usize, value: M::Value) -> Result<(), RangeError> {
+        check_index(i, self.n)?;
+        let leaf = i + self.size;
+
+        for level in (1..=self.log).rev() {
+            self.push_down(leaf >> level);
+        }
+        self.tree[leaf] = value;
+        for level in 1..=self.log {
+            self.pull(leaf >> level);
+        }
+
+        Ok(())
+    }
+
+    /// Current value of `a[i]`, with all the pending updates applied.
+    pub fn get(&self, i: usize) -> Result<M::Value, RangeError> {
+        check_index(i, self.n)?;
+        let leaf = i + self.size;
+        Ok(self.value_with(leaf, &self.path_pending(leaf)[1]))
+    }
+
+    /// The whole current array, with all the pending updates applied.
+    pub fn to_vec(&self) -> Vec<M::Value> {
+        // below[k]: the tags pending on the children of node k, parents come before their children
+        let mut below: Vec<Option<A::Tag>> = vec![None; self.size];
+        for k in 1..self.size {
+            below[k] = Self::compose(&below[k >> 1], &self.lazy[k]);
+        }
+
+        (self.size..self.size + self.n)
+            .map(|leaf| self.value_with(leaf, &below[leaf >> 1]))
+            .collect()
+    }
+
+    // ---------------------- SEARCH ----------------------
+    // As in the updates, the nodes read while climbing from a border are children of the nodes on
+    // the path from the root to that border, so their pending tags are known from `path_pending`.
+
+    /// Largest `r` such that `pred` holds on the combination of `a[l..r]`, with `l <= r <= n`.
+    /// `pred` must hold on the identity and be monotone: once false, it stays false when the range grows.
+    pub fn max_right<F>(&self, l: usize, pred: F) -> Result<usize, RangeError>
+    where
+        F: Fn(&M::Value) -> bool,
+    {
+        if l > self.n {
+            return Err(RangeError::OutOfBounds {
+                end: l,
+                len: self.n,
+            });
+        }
+        if l == self.n {
+            return Ok(self.n);
+        }
+
+        let mut node = l + self.size;
+        let path = self.path_pending(node);
+
+        let mut acc = M::identity();
+        loop {
+            // go up while the node is a left child: its parent starts at the same position
+            while node.is_multiple_of(2) {
+                node >>= 1;
+            }
+
+            let mut pending = path[self.level_of(node) + 1].clone();
+            let combined = M::combine(&acc, &self.value_with(node, &pending));
+            if !pred(&combined) {
+                // the answer is inside this node, go down to the first leaf where pred fails
+                while node < self.size {
+                    pending = Self::compose(&pending, &self.lazy[node]);
+                    node *= 2;
+                    let combined = M::combine(&acc, &self.value_with(node, &pending));
+                    if pred(&combined) {
+                        acc = combined;
+                        node += 1;
+                    }
+                }
+                return Ok(node - self.size);
+            }
+
+            acc = combined;
+            node += 1;
+            if node.is_power_of_two() {
+                // went past the last node of a level: the whole array is accepted
+                return Ok(self.n);
+            }
+        }
+    }
+
+    /// Smallest `l` such that `pred` holds on the combination of `a[l..r]`, with `0 <= l <= r`.
+    /// `pred` must hold on the identity and be monotone: once false, it stays false when the range grows.
+    pub fn min_left<F>(&self, r: usize, pred: F) -> Result<usize, RangeError>
+    where
+        F: Fn(&M::Value) -> bool,
+    {
+        let (_, r) = to_half_open(..r, self.n)?;
+        if r == 0 {
+            return Ok(0);
+        }
+
+        let mut node = r + self.size;
+        let path = self.path_pending(node - 1);
+
+        let mut acc = M::identity();
+        loop {
+            node -= 1;
+            // go up while the node is a right child: its parent ends at the same position
+            while node > 1 && !node.is_multiple_of(2) {
+                node >>= 1;
+            }
+
+            let mut pending = path[self.level_of(node) + 1].clone();
+            let combined = M::combine(&self.value_with(node, &pending), &acc);
+            if !pred(&combined) {
+                // the answer is inside this node, go down to the last leaf where pred fails
+                while node < self.size {
+                    pending = Self::compose(&pending, &self.lazy[node]);
+                    node = 2 * node + 1;
+                    let combined = M::combine(&self.value_with(node, &pending), &acc);
+                    if pred(&combined) {
+                        acc = combined;
+                        node -= 1;
+                    }
+                }
+                return Ok(node + 1 - self.size);
+            }
+
+            acc = combined;
+            if node.is_power_of_two() {
+                // reached the first node of a level: the whole prefix is accepted
+                return Ok(0);
+            }
+        }
+    }
+
+    /// First position `i` in `range` such that `matches(a[i])` holds, see `SegmentTree::find_first`:
+    /// the nodes where `may_contain` fails on the combination of their range are skipped.
+    pub fn find_first<F, G>(
+        &self,
+        range: impl RangeBounds<usize>,
+        may_contain: F,
+        matches: G,
+    ) -> Result<Option<usize>, RangeError>
+    where
+        F: Fn(&M::Value) -> bool,
+        G: Fn(&M::Value) -> bool,
+    {
+        let (l, r) = to_half_open(range, self.n)?;
+
+        // top-down from the root, the right child is pushed first so that the left one comes out first
+        let mut to_visit = vec![(1, None)];
+        while let Some((node, pending)) = to_visit.pop() {
+            let start = self.node_start(node);
+            let end = start + self.node_len(node);
+            if start >= r || end <= l {
+                // no overlap
+                continue;
+            }
+
+            let value = self.value_with(node, &pending);
+            if !may_contain(&value) {
+                // no position of the node can match
+                continue;
+            }
+
+            if node >= self.size {
+                if matches(&value) {
+                    return Ok(Some(start));
+                }
+                continue;
+            }
+
+            let pending = Self::compose(&pending, &self.lazy[node]);
+            to_visit.push((2 * node + 1, pending.clone()));
+            to_visit.push((2 * node, pending));
+        }
+
+        Ok(None)
+    }
+
+    // --------------------- HELPERS ---------------------
+
+    // number of positions covered by node k: the leaves are at depth `log`
+    fn node_len(&self, k: usize) -> usize {
+        let depth = usize::BITS - 1 - k.leading_zeros();
+        self.size >> depth
+    }
+
+    // first position covered by node k
+    fn node_start(&self, k: usize) -> usize {
+        let depth = usize::BITS - 1 - k.leading_zeros();
+        (k - (1 << depth)) * self.node_len(k)
+    }
+
+    // height of node k above the leaves (0 for a leaf, `log` for the root)
+    fn level_of(&self, k: usize) -> usize {
+        self.log as usize - (usize::BITS - 1 - k.leading_zeros()) as usize
+    }
+
+    // value of node k once the tags pending on it (from its ancestors) are applied
+    fn value_with(&self, k: usize, pending: &Option<A::Tag>) -> M::Value {
+        match pending {
+            Some(tag) => A::apply(tag, &self.tree[k], self.node_len(k)),
+            None => self.tree[k].clone(),
+        }
+    }
+
+    // pending[level]: the tags pending on the children of the node at `level` on the path from the
+    // root to `leaf`, i.e. the composition of the tags of that node and of its ancestors
+    // (`pending[log + 1]` is for the root itself, which has no ancestor)
+    fn path_pending(&self, leaf: usize) -> Vec<Option<A::Tag>> {
+        let log = self.log as usize;
+        let mut pending = vec![None; log + 2];
+        for level in (1..=log).rev() {
+            pending[level] = Self::compose(&pending[level + 1], &self.lazy[leaf >> level]);
+        }
+        pending
+    }
+
+    // composition of an outer and an inner optional tag
+    fn compose(outer: &Option<A::Tag>, inner: &Option<A::Tag>) -> Option<A::Tag> {
+        match (outer, inner) {
+            (Some(outer), Some(inner)) => Some(A::compose(outer, inner)),
+            (Some(tag), None) | (None, Some(tag)) => Some(tag.clone()),
+            (None, None) => None,
+        }
+    }
+
+    // recompute the value of a node from its children
+    fn pull(&mut self, k: usize) {
+        self.tree[k] = M::combine(&self.tree[2 * k], &self.tree[2 * k + 1]);
+    }
+
+    // apply a tag to node k, and remember it for the children if it is not a leaf
+    fn apply_tag(&mut self, k: usize, tag: &A::Tag) {
+        self.tree[k] = A::apply(tag, &self.tree[k], self.node_len(k));
+
+        if k < self.size {
+            self.lazy[k] = Some(match &self.lazy[k] {
+                // the node already has a pending tag, the new one comes after it
+                Some(old_tag) => A::compose(tag, old_tag),
+                None => tag.clone(),
+            });
+        }
+    }
+
+    // push the pending tag of node k down to its children
+    fn push_down(&mut self, k: usize) {
+        if let Some(tag) = self.lazy[k].take() {
+            self.apply_tag(2 * k, &tag);
+            self.apply_tag(2 * k + 1, &tag);
+        }
+    }
+
+    // push down the tags on the paths from the root to the leaves l and r - 1, top-down,
+    // skipping the levels where the border falls between two nodes
+    fn push_borders(&mut self, l: usize, r: usize) {
+        for i in (1..=self.log).rev() {
+            if (l >> i) << i != l {
+                self.push_down(l >> i);
+            }
+            if (r >> i) << i != r {
+                self.push_down((r - 1) >> i);
+            }
+        }
+    }
+}
+
+impl<M: Monoid, A: RangeAdd<M>> IterativeSegmentTree<M, A> {
+    /// `a[i] = a[i] + delta` for every `i` in `range`.
+    pub fn add_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        delta: M::Value,
+    ) -> Result<(), RangeError> {
+        self.apply_range(range, A::add_tag(delta))
+    }
+}
+
+impl<M: Monoid, A: RangeAssign<M>> IterativeSegmentTree<M, A> {
+    /// `a[i] = value` for every `i` in `range`.
+    pub fn assign_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        value: M::Value,
+    ) -> Result<(), RangeError> {
+        self.apply_range(range, A::assign_tag(value))
+    }
+}
+
+impl<M: Monoid, T> IterativeSegmentTree<M, ChMin<T>>
+where
+    ChMin<T>: LazyAction<M, Tag = T>,
+{
+    /// `a[i] = min(a[i], t)` for every `i` in `range`.
+    pub fn update_range(&mut self, range: impl RangeBounds<usize>, t: T) -> Result<(), RangeError> {
+        self.apply_range(range, t)
+    }
+}
+
+impl<T: Bounded, A: LazyAction<Max<T>>> IterativeSegmentTree<Max<T>, A> {
+    /// Maximum of `range` (`T::min_value()` for an empty range).
+    pub fn max_query(&self, range: impl RangeBounds<usize>) -> Result<T, RangeError> {
+        self.query(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lazy::{Add, AddAssign, Affine, AffineTag};
+    use crate::monoid::{Max, Min, Sum};
+    use crate::test_util::Lcg;
+    use crate::SegmentTree;
+
+    #[test]
+    fn test_matches_recursive_tree() {
+        // sizes around powers of two, to exercise the padding
+        for n in [1, 2, 5, 8, 13, 16, 17] {
+            // non-negative values, so that the prefix sums only grow and the searches are well defined
+            let a: Vec<i64> = (0..n as i64).map(|i| (i * 7) % 11).collect();
+            let mut iterative = IterativeSegmentTree::<Sum, AddAssign>::init(&a);
+            let mut recursive = SegmentTree::<Sum, AddAssign>::init(&a);
+            let mut min_tree = IterativeSegmentTree::<Min, AddAssign>::init(&a);
+            let mut recursive_min = SegmentTree::<Min, AddAssign>::init(&a);
+
+            let mut rng = Lcg(n as u64);
+            for _ in 0..300 {
+                let x = rng.next(n + 1);
+                let y = rng.next(n + 1);
+                let range = x.min(y)..x.max(y);
+                let v = rng.next(11) as i64;
+
+                match rng.next(5) {
+                    0 => {
+                        iterative.add_range(range.clone(), v).unwrap();
+                        recursive.add_range(range.clone(), v).unwrap();
+                        min_tree.add_range(range.clone(), v).unwrap();
+                        recursive_min.add_range(range, v).unwrap();
+                    }
+                    1 => {
+                        iterative.assign_range(range.clone(), v).unwrap();
+                        recursive.assign_range(range.clone(), v).unwrap();
+                        min_tree.assign_range(range.clone(), v).unwrap();
+                        recursive_min.assign_range(range, v).unwrap();
+                    }
+                    2 => {
+                        let i = rng.next(n);
+                        iterative.set(i, v).unwrap();
+                        recursive.set(i, v).unwrap();
+                        assert_eq!(iterative.get(i), recursive.get(i));
+                    }
+                    3 => {
+                        let bound = rng.next(60) as i64;
+                        assert_eq!(
+                            min_tree.find_first(range.clone(), |m| *m <= bound, |m| *m == bound),
+                            recursive_min.find_first(
+                                range.clone(),
+                                |m| *m <= bound,
+                                |m| *m == bound
+                            )
+                        );
+                        let l = rng.next(n + 1);
+                        let r = rng.next(n + 1);
+                        assert_eq!(
+                            iterative.max_right(l, |s| *s <= bound),
+                            recursive.max_right(l, |s| *s <= bound)
+                        );
+                        assert_eq!(
+                            iterative.min_left(r, |s| *s <= bound),
+                            recursive.min_left(r, |s| *s <= bound)
+                        );
+                    }
+                    _ => {
+                        assert_eq!(
+                            iterative.query(range.clone()),
+                            recursive.query(range.clone())
+                        );
+                        assert_eq!(min_tree.query(range.clone()), recursive_min.query(range));
+                    }
+                }
+            }
+
+            assert_eq!(iterative.to_vec(), recursive.to_vec());
+            assert_eq!(min_tree.to_vec(), recursive_min.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_iterative_tree_basics() {
+        let mut max_tree = IterativeSegmentTree::<Max>::init(&[5, 3, 8, 6, 1, 4]);
+        assert_eq!(max_tree.len(), 6);
+        assert_eq!(max_tree.query(..), Ok(8));
+        assert_eq!(max_tree.query(3..), Ok(6));
+        assert_eq!(max_tree.query(4..4), Ok(i64::MIN));
+        assert_eq!(
+            max_tree.query(..=6),
+            Err(RangeError::OutOfBounds { end: 7, len: 6 })
+        );
+        assert_eq!(
+            max_tree.set(6, 0),
+            Err(RangeError::IndexOutOfBounds { index: 6, len: 6 })
+        );
+
+        // the affine tags need the length of the nodes
+        let mut sum_tree = IterativeSegmentTree::<Sum, Affine>::init(&[1, 2, 3, 4, 5]);
+        sum_tree.apply_range(1..4, AffineTag::new(2, 1)).unwrap();
+        sum_tree.add_range(.., 1).unwrap();
+        assert_eq!(sum_tree.to_vec(), vec![2, 6, 8, 10, 6]);
+        assert_eq!(sum_tree.query(1..=2), Ok(14));
+
+        let add_tree = IterativeSegmentTree::<Sum, Add>::init(&[]);
+        assert!(add_tree.is_empty());
+        assert_eq!(add_tree.query(..), Ok(0));
+        assert_eq!(add_tree.max_right(0, |_| true), Ok(0));
+        assert_eq!(
+            add_tree.max_right(1, |_| true),
+            Err(RangeError::OutOfBounds { end: 1, len: 0 })
+        );
+        assert_eq!(add_tree.to_vec(), Vec::<i64>::new());
+        assert_eq!(add_tree.find_first(.., |_| true, |_| true), Ok(None));
+
+        // chmin updates, read through a shared reference
+        let mut chmin_tree = IterativeSegmentTree::<Max, ChMin>::init(&[3, 8, 4, 9, 7, 2]);
+        chmin_tree.update_range(..4, 6).unwrap();
+        let shared = &chmin_tree;
+        assert_eq!(shared.max_query(..), Ok(7));
+        assert_eq!(shared.max_query(..4), Ok(6));
+        assert_eq!(shared.get(3), Ok(6));
+        assert_eq!(shared.find_first(.., |m| *m >= 7, |m| *m >= 7), Ok(Some(4)));
+        assert_eq!(shared.to_vec(), vec![3, 6, 4, 6, 7, 2]);
+    }
+}
@@ -0,0 +1,413 @@
+use std::marker::PhantomData;
+
+use crate::monoid::{Max, Min, MinCount, MinMax, Monoid, Sum};
+use crate::num::{Bounded, Number};
+
+/// A range update that can be applied lazily on a `SegmentTree` built on the monoid `M`.
+///
+/// A pending update is stored as a `Tag` on the highest nodes covering the range, and pushed
+/// down to the children only when a later operation needs to go below them. For this to work:
+/// - `apply` must distribute over the monoid: applying a tag to `combine(a, b)` gives the same
+///   result as combining the tag applied to `a` and to `b`;
+/// - `compose(outer, inner)` must behave like applying `inner` first and then `outer`.
+pub trait LazyAction<M: Monoid> {
+    type Tag: Clone;
+
+    fn compose(outer: &Self::Tag, inner: &Self::Tag) -> Self::Tag;
+
+    // applies the tag to the summary `value` of a segment of `len` elements
+    fn apply(tag: &Self::Tag, value: &M::Value, len: usize) -> M::Value;
+}
+
+/// No range update, for trees that only answer queries.
+pub struct NoAction;
+
+impl<M: Monoid> LazyAction<M> for NoAction {
+    type Tag = ();
+
+    fn compose(_outer: &(), _inner: &()) {}
+
+    fn apply(_tag: &(), value: &M::Value, _len: usize) -> M::Value {
+        value.clone()
+    }
+}
+
+/// `a[i] = min(a[i], t)`, on `Min` and `Max` trees.
+pub struct ChMin<T = i64>(PhantomData<T>);
+
+impl<T: Bounded> LazyAction<Min<T>> for ChMin<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, inner: &T) -> T {
+        *outer.min(inner)
+    }
+
+    fn apply(tag: &T, value: &T, _len: usize) -> T {
+        *value.min(tag)
+    }
+}
+
+impl<T: Bounded> LazyAction<Max<T>> for ChMin<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, inner: &T) -> T {
+        *outer.min(inner)
+    }
+
+    fn apply(tag: &T, value: &T, _len: usize) -> T {
+        *value.min(tag)
+    }
+}
+
+/// `a[i] = a[i] + delta`.
+pub struct Add<T = i64>(PhantomData<T>);
+
+impl<T: Number> LazyAction<Sum<T>> for Add<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, inner: &T) -> T {
+        *outer + *inner
+    }
+
+    fn apply(tag: &T, value: &T, len: usize) -> T {
+        *value + *tag * T::from_usize(len)
+    }
+}
+
+impl<T: Number + Bounded> LazyAction<Min<T>> for Add<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, inner: &T) -> T {
+        *outer + *inner
+    }
+
+    fn apply(tag: &T, value: &T, _len: usize) -> T {
+        *value + *tag
+    }
+}
+
+impl<T: Number + Bounded> LazyAction<Max<T>> for Add<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, inner: &T) -> T {
+        *outer + *inner
+    }
+
+    fn apply(tag: &T, value: &T, _len: usize) -> T {
+        *value + *tag
+    }
+}
+
+impl<T: Number + Bounded> LazyAction<MinMax<T>> for Add<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, inner: &T) -> T {
+        *outer + *inner
+    }
+
+    fn apply(tag: &T, value: &(T, T), _len: usize) -> (T, T) {
+        (value.0 + *tag, value.1 + *tag)
+    }
+}
+
+impl<T: Number + Bounded> LazyAction<MinCount<T>> for Add<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, inner: &T) -> T {
+        *outer + *inner
+    }
+
+    fn apply(tag: &T, value: &(T, usize), _len: usize) -> (T, usize) {
+        (value.0 + *tag, value.1)
+    }
+}
+
+/// `a[i] = v`.
+pub struct Assign<T = i64>(PhantomData<T>);
+
+impl<T: Number> LazyAction<Sum<T>> for Assign<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, _inner: &T) -> T {
+        *outer
+    }
+
+    fn apply(tag: &T, _value: &T, len: usize) -> T {
+        *tag * T::from_usize(len)
+    }
+}
+
+impl<T: Bounded> LazyAction<Min<T>> for Assign<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, _inner: &T) -> T {
+        *outer
+    }
+
+    fn apply(tag: &T, _value: &T, _len: usize) -> T {
+        *tag
+    }
+}
+
+impl<T: Bounded> LazyAction<Max<T>> for Assign<T> {
+    type Tag = T;
+
+    fn compose(outer: &T, _inner: &T) -> T {
+        *outer
+    }
+
+    fn apply(tag: &T, _value: &T, _len: usize) -> T {
+        *tag
+    }
+}
+
+/// `a[i] = mul * a[i] + add`, on `Sum` trees.
+pub struct Affine<T = i64>(PhantomData<T>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AffineTag<T> {
+    pub mul: T,
+    pub add: T,
+}
+
+impl<T> AffineTag<T> {
+    pub fn new(mul: T, add: T) -> Self {
+        Self { mul, add }
+    }
+}
+
+impl<T: Number> LazyAction<Sum<T>> for Affine<T> {
+    type Tag = AffineTag<T>;
+
+    // outer(inner(x)) = o.mul * (i.mul * x + i.add) + o.add
+    fn compose(outer: &AffineTag<T>, inner: &AffineTag<T>) -> AffineTag<T> {
+        AffineTag {
+            mul: outer.mul * inner.mul,
+            add: outer.mul * inner.add + outer.add,
+        }
+    }
+
+    // every element is mapped, so the sum becomes mul * sum + add * len
+    fn apply(tag: &AffineTag<T>, value: &T, len: usize) -> T {
+        tag.mul * *value + tag.add * T::from_usize(len)
+    }
+}
+
+/// `a[i] = v` and `a[i] = a[i] + delta` on the same tree: the tag is an optional assignment
+/// followed by an addition.
+pub struct AddAssign<T = i64>(PhantomData<T>);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AddAssignTag<T> {
+    pub assign: Option<T>,
+    pub add: T,
+}
+
+impl<T: Number> AddAssignTag<T> {
+    // the new tag replaces an assignment, and adds up with a previous addition
+    fn then(&self, outer: &Self) -> Self {
+        match outer.assign {
+            Some(_) => *outer,
+            None => AddAssignTag {
+                assign: self.assign,
+                add: self.add + outer.add,
+            },
+        }
+    }
+}
+
+impl<T: Number> LazyAction<Sum<T>> for AddAssign<T> {
+    type Tag = AddAssignTag<T>;
+
+    fn compose(outer: &Self::Tag, inner: &Self::Tag) -> Self::Tag {
+        inner.then(outer)
+    }
+
+    fn apply(tag: &Self::Tag, value: &T, len: usize) -> T {
+        let len = T::from_usize(len);
+        let base = tag.assign.map_or(*value, |v| v * len);
+        base + tag.add * len
+    }
+}
+
+impl<T: Number + Bounded> LazyAction<Min<T>> for AddAssign<T> {
+    type Tag = AddAssignTag<T>;
+
+    fn compose(outer: &Self::Tag, inner: &Self::Tag) -> Self::Tag {
+        inner.then(outer)
+    }
+
+    fn apply(tag: &Self::Tag, value: &T, _len: usize) -> T {
+        tag.assign.unwrap_or(*value) + tag.add
+    }
+}
+
+impl<T: Number + Bounded> LazyAction<Max<T>> for AddAssign<T> {
+    type Tag = AddAssignTag<T>;
+
+    fn compose(outer: &Self::Tag, inner: &Self::Tag) -> Self::Tag {
+        inner.then(outer)
+    }
+
+    fn apply(tag: &Self::Tag, value: &T, _len: usize) -> T {
+        tag.assign.unwrap_or(*value) + tag.add
+    }
+}
+
+// ---------------------- COMMON UPDATES ----------------------
+// Actions that can express "add delta" or "assign v" provide the matching tag, which gives the
+// tree its `add_range` and `assign_range` methods.
+
+pub trait RangeAdd<M: Monoid>: LazyAction<M> {
+    fn add_tag(delta: M::Value) -> Self::Tag;
+}
+
+pub trait RangeAssign<M: Monoid>: LazyAction<M> {
+    fn assign_tag(value: M::Value) -> Self::Tag;
+}
+
+macro_rules! impl_range_updates {
+    ($($monoid:ident),*) => {
+        $(
+            impl<T: Number + Bounded> RangeAdd<$monoid<T>> for Add<T> {
+                fn add_tag(delta: T) -> T {
+                    delta
+                }
+            }
+
+            impl<T: Number + Bounded> RangeAssign<$monoid<T>> for Assign<T> {
+                fn assign_tag(value: T) -> T {
+                    value
+                }
+            }
+
+            impl<T: Number + Bounded> RangeAdd<$monoid<T>> for AddAssign<T> {
+                fn add_tag(delta: T) -> AddAssignTag<T> {
+                    AddAssignTag { assign: None, add: delta }
+                }
+            }
+
+            impl<T: Number + Bounded> RangeAssign<$monoid<T>> for AddAssign<T> {
+                fn assign_tag(value: T) -> AddAssignTag<T> {
+                    AddAssignTag { assign: Some(value), add: T::zero() }
+                }
+            }
+        )*
+    };
+}
+
+impl_range_updates!(Sum, Min, Max);
+
+impl<T: Number> RangeAdd<Sum<T>> for Affine<T> {
+    fn add_tag(delta: T) -> AffineTag<T> {
+        AffineTag::new(T::one(), delta)
+    }
+}
+
+impl<T: Number> RangeAssign<Sum<T>> for Affine<T> {
+    fn assign_tag(value: T) -> AffineTag<T> {
+        AffineTag::new(T::zero(), value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+    use crate::SegmentTree;
+
+    #[test]
+    fn test_range_add() {
+        let mut a: Vec<i64> = vec![3, -1, 4, 1, -5, 9, 2, -6, 5, 3];
+        let mut sum_tree = SegmentTree::<Sum, Add>::init(&a);
+        let mut min_tree = SegmentTree::<Min, Add>::init(&a);
+        let mut max_tree = SegmentTree::<Max, Add>::init(&a);
+        let pairs: Vec<(i64, i64)> = a.iter().map(|&v| (v, v)).collect();
+        let mut min_max_tree = SegmentTree::<MinMax, Add>::init(&pairs);
+
+        let mut rng = Lcg(1);
+        for _ in 0..500 {
+            let x = rng.next(a.len() + 1);
+            let y = rng.next(a.len() + 1);
+            let range = x.min(y)..x.max(y);
+
+            if rng.next(2) == 0 {
+                let delta = rng.next(21) as i64 - 10;
+                sum_tree.apply_range(range.clone(), delta).unwrap();
+                min_tree.apply_range(range.clone(), delta).unwrap();
+                max_tree.apply_range(range.clone(), delta).unwrap();
+                min_max_tree.apply_range(range.clone(), delta).unwrap();
+                a[range.clone()].iter_mut().for_each(|v| *v += delta);
+            } else {
+                let slice = &a[range.clone()];
+                assert_eq!(sum_tree.query(range.clone()), Ok(slice.iter().sum()));
+                let min = slice.iter().min().copied().unwrap_or(i64::MAX);
+                let max = slice.iter().max().copied().unwrap_or(i64::MIN);
+                assert_eq!(min_tree.query(range.clone()), Ok(min));
+                assert_eq!(max_tree.query(range.clone()), Ok(max));
+                assert_eq!(min_max_tree.query(range), Ok((min, max)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_assign() {
+        let mut a: Vec<i64> = vec![7; 13];
+        let mut sum_tree = SegmentTree::<Sum, Assign>::init(&a);
+        let mut max_tree = SegmentTree::<Max, Assign>::init(&a);
+
+        let mut rng = Lcg(2);
+        for _ in 0..500 {
+            let x = rng.next(a.len() + 1);
+            let y = rng.next(a.len() + 1);
+            let range = x.min(y)..x.max(y);
+
+            if rng.next(2) == 0 {
+                let value = rng.next(100) as i64;
+                sum_tree.apply_range(range.clone(), value).unwrap();
+                max_tree.apply_range(range.clone(), value).unwrap();
+                a[range.clone()].iter_mut().for_each(|v| *v = value);
+            } else {
+                let slice = &a[range.clone()];
+                assert_eq!(sum_tree.query(range.clone()), Ok(slice.iter().sum()));
+                let max = slice.iter().max().copied().unwrap_or(i64::MIN);
+                assert_eq!(max_tree.query(range), Ok(max));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_affine() {
+        let mut a: Vec<i64> = (1..=9).collect();
+        let mut tree = SegmentTree::<Sum, Affine>::init(&a);
+
+        // x -> 2x + 1 on [1, 5], then x -> -x + 3 on [3, 8]: composed on [3, 5]
+        tree.apply_range(1..=5, AffineTag::new(2, 1)).unwrap();
+        tree.apply_range(3..=8, AffineTag::new(-1, 3)).unwrap();
+        a[1..6].iter_mut().for_each(|v| *v = 2 * *v + 1);
+        a[3..9].iter_mut().for_each(|v| *v = -*v + 3);
+
+        for l in 0..a.len() {
+            for r in l..a.len() {
+                assert_eq!(tree.query(l..=r), Ok(a[l..=r].iter().sum()));
+            }
+        }
+
+        // the composition matches applying the two maps in order
+        let composed =
+            <Affine as LazyAction<Sum>>::compose(&AffineTag::new(-1, 3), &AffineTag::new(2, 1));
+        assert_eq!(composed, AffineTag::new(-2, 2));
+    }
+
+    #[test]
+    fn test_chmin_on_max_tree() {
+        let a = [5, 3, 8, 6, 1, 4];
+        let mut tree = SegmentTree::<Max, ChMin>::init(&a);
+
+        tree.update_range(1..=4, 4).unwrap();
+        tree.update_range(2..=2, 7).unwrap();
+        assert_eq!(tree.max_query(..), Ok(5));
+        assert_eq!(tree.max_query(1..=4), Ok(4));
+        assert_eq!(tree.max_query(5..), Ok(4));
+    }
+}
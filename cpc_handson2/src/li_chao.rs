@@ -0,0 +1,497 @@
+use std::marker::PhantomData;
+
+use crate::monoid::{Max, Min};
+
+/// Line `y = a·x + b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line {
+    pub a: i64,
+    pub b: i64,
+}
+
+impl Line {
+    pub fn new(a: i64, b: i64) -> Self {
+        Self { a, b }
+    }
+
+    pub fn eval(&self, x: i64) -> i64 {
+        self.a * x + self.b
+    }
+
+    fn negate(&self) -> Self {
+        Self::new(-self.a, -self.b)
+    }
+}
+
+/// Whether a Li Chao tree answers minimums (`Min<i64>`) or maximums (`Max<i64>`).
+///
+/// The trees only ever minimize: for `Max` the lines are stored negated, and so are the answers.
+pub trait Extremum {
+    // 1 to keep the lines as they are, -1 to negate them
+    fn sign() -> i64;
+}
+
+impl Extremum for Min<i64> {
+    fn sign() -> i64 {
+        1
+    }
+}
+
+impl Extremum for Max<i64> {
+    fn sign() -> i64 {
+        -1
+    }
+}
+
+// the line actually stored for `line`, and back
+fn oriented<E: Extremum>(line: Line) -> Line {
+    if E::sign() < 0 {
+        line.negate()
+    } else {
+        line
+    }
+}
+
+/// Li Chao tree over a fixed set of query abscissas: inserts lines (or segments of lines) and
+/// answers the minimum or maximum of the lines at one of the abscissas, both in O(log n)
+/// (O(log² n) to insert a segment), as needed by convex-hull-trick DPs.
+///
+/// Each node keeps the line that wins at its middle abscissa; the line that loses can only win on
+/// one side of it, and is pushed down on that side only. The answer at `x` is the best line on the
+/// path from the root to the leaf of `x`.
+///
+/// `a·x + b` must fit in an `i64` for every line and abscissa.
+pub struct LiChaoTree<E: Extremum = Min<i64>> {
+    xs: Vec<i64>, // sorted distinct abscissas, the leaves of the tree
+    lines: Vec<Option<Line>>,
+    _extremum: PhantomData<E>,
+}
+
+impl<E: Extremum> LiChaoTree<E> {
+    // --------------------- CONSTRUCTOR ---------------------
+
+    /// Empty tree answering queries at the abscissas `xs` (e.g. `0..n` for a dense domain).
+    pub fn new(xs: impl IntoIterator<Item = i64>) -> Self {
+        let mut xs: Vec<i64> = xs.into_iter().collect();
+        xs.sort_unstable();
+        xs.dedup();
+
+        Self {
+            lines: vec![None; 4 * xs.len()],
+            xs,
+            _extremum: PhantomData,
+        }
+    }
+
+    /// Number of abscissas.
+    pub fn len(&self) -> usize {
+        self.xs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.xs.is_empty()
+    }
+
+    // --------------------- UPDATE ---------------------
+
+    /// Inserts the line over the whole domain.
+    pub fn add_line(&mut self, line: Line) {
+        if !self.is_empty() {
+            self.insert(0, 0, self.len() - 1, oriented::<E>(line));
+        }
+    }
+
+    /// Inserts the line restricted to the abscissas of the closed segment `[x_left, x_right]`.
+    pub fn add_segment(&mut self, line: Line, x_left: i64, x_right: i64) {
+        let l = self.xs.partition_point(|&x| x < x_left);
+        let r = self.xs.partition_point(|&x| x <= x_right);
+        if l < r {
+            self.add_segment_rec(0, 0, self.len() - 1, l, r - 1, oriented::<E>(line));
+        }
+    }
+
+    fn add_segment_rec(
+        &mut self,
+        node: usize,
+        start: usize,
+        end: usize,
+        l: usize,
+        r: usize,
+        line: Line,
+    ) {
+        if start > r || end < l {
+            // no overlap
+            return;
+        }
+
+        if start >= l && end <= r {
+            // total overlap
+            self.insert(node, start, end, line);
+            return;
+        }
+
+        let mid = (start + end) / 2;
+        self.add_segment_rec(2 * node + 1, start, mid, l, r, line);
+        self.add_segment_rec(2 * node + 2, mid + 1, end, l, r, line);
+    }
+
+    // keep the best line of the node at its middle, and push the other one down
+    fn insert(&mut self, node: usize, start: usize, end: usize, mut line: Line) {
+        let Some(mut current) = self.lines[node] else {
+            self.lines[node] = Some(line);
+            return;
+        };
+
+        let mid = (start + end) / 2;
+        let better_at_start = line.eval(self.xs[start]) < current.eval(self.xs[start]);
+        let better_at_mid = line.eval(self.xs[mid]) < current.eval(self.xs[mid]);
+        if better_at_mid {
+            std::mem::swap(&mut line, &mut current);
+            self.lines[node] = Some(current);
+        }
+
+        if start == end {
+            return;
+        }
+        // the loser can only win on the side where the two lines cross
+        if better_at_start != better_at_mid {
+            self.insert(2 * node + 1, start, mid, line);
+        } else {
+            self.insert(2 * node + 2, mid + 1, end, line);
+        }
+    }
+
+    // --------------------- QUERY ---------------------
+
+    /// Minimum (or maximum) at `x` of the lines inserted so far, or `None` if no line covers `x`.
+    ///
+    /// # Panics
+    /// Panics if `x` is not one of the abscissas of the tree.
+    pub fn query(&self, x: i64) -> Option<i64> {
+        let i = self
+            .xs
+            .binary_search(&x)
+            .unwrap_or_else(|_| panic!("{} is not an abscissa of the tree", x));
+
+        let mut best: Option<i64> = None;
+        let (mut node, mut start, mut end) = (0, 0, self.len() - 1);
+        loop {
+            if let Some(line) = self.lines[node] {
+                let y = line.eval(x);
+                best = Some(best.map_or(y, |best| best.min(y)));
+            }
+            if start == end {
+                break;
+            }
+
+            let mid = (start + end) / 2;
+            if i <= mid {
+                node = 2 * node + 1;
+                end = mid;
+            } else {
+                node = 2 * node + 2;
+                start = mid + 1;
+            }
+        }
+        best.map(|y| E::sign() * y)
+    }
+}
+
+struct Node {
+    line: Option<Line>,
+    left: Option<usize>, // created when a line is first pushed down on that side
+    right: Option<usize>,
+}
+
+/// Li Chao tree over every integer of `lo..hi`, for domains too large to list (e.g. `-10^9..10^9`).
+/// Nodes are allocated in an arena when a line is pushed down to them, so each insertion creates
+/// at most O(log(hi - lo)) nodes (one for a whole-domain line).
+///
+/// `hi - lo` and `a·x + b` must fit in an `i64`.
+pub struct DynamicLiChaoTree<E: Extremum = Min<i64>> {
+    lo: i64,
+    hi: i64,
+    nodes: Vec<Node>,
+    _extremum: PhantomData<E>,
+}
+
+impl<E: Extremum> DynamicLiChaoTree<E> {
+    // --------------------- CONSTRUCTOR ---------------------
+
+    /// Empty tree over the abscissas `lo..hi`.
+    pub fn new(lo: i64, hi: i64) -> Self {
+        let mut tree = Self {
+            lo,
+            hi,
+            nodes: Vec::new(),
+            _extremum: PhantomData,
+        };
+        tree.new_node();
+        tree
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lo >= self.hi
+    }
+
+    /// Number of allocated nodes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    // --------------------- UPDATE ---------------------
+
+    /// Inserts the line over the whole domain.
+    pub fn add_line(&mut self, line: Line) {
+        if !self.is_empty() {
+            self.insert(0, self.lo, self.hi - 1, oriented::<E>(line));
+        }
+    }
+
+    /// Inserts the line restricted to the closed segment `[x_left, x_right]`.
+    pub fn add_segment(&mut self, line: Line, x_left: i64, x_right: i64) {
+        let (l, r) = (x_left.max(self.lo), x_right.min(self.hi - 1));
+        if l <= r {
+            self.add_segment_rec(0, self.lo, self.hi - 1, l, r, oriented::<E>(line));
+        }
+    }
+
+    fn add_segment_rec(&mut self, node: usize, start: i64, end: i64, l: i64, r: i64, line: Line) {
+        if start > r || end < l {
+            // no overlap
+            return;
+        }
+
+        if start >= l && end <= r {
+            // total overlap
+            self.insert(node, start, end, line);
+            return;
+        }
+
+        let mid = start + (end - start).div_euclid(2);
+        let left = self.child(node, true);
+        self.add_segment_rec(left, start, mid, l, r, line);
+        let right = self.child(node, false);
+        self.add_segment_rec(right, mid + 1, end, l, r, line);
+    }
+
+    // same as `LiChaoTree::insert`, creating the children on the way
+    fn insert(&mut self, node: usize, start: i64, end: i64, mut line: Line) {
+        let Some(mut current) = self.nodes[node].line else {
+            self.nodes[node].line = Some(line);
+            return;
+        };
+
+        let mid = start + (end - start).div_euclid(2);
+        let better_at_start = line.eval(start) < current.eval(start);
+        let better_at_mid = line.eval(mid) < current.eval(mid);
+        if better_at_mid {
+            std::mem::swap(&mut line, &mut current);
+            self.nodes[node].line = Some(current);
+        }
+
+        if start == end {
+            return;
+        }
+        if better_at_start != better_at_mid {
+            let left = self.child(node, true);
+            self.insert(left, start, mid, line);
+        } else {
+            let right = self.child(node, false);
+            self.insert(right, mid + 1, end, line);
+        }
+    }
+
+    // --------------------- QUERY ---------------------
+
+    /// Minimum (or maximum) at `x` of the lines inserted so far, or `None` if no line covers `x`.
+    ///
+    /// # Panics
+    /// Panics if `x` is not in `lo..hi`.
+    pub fn query(&self, x: i64) -> Option<i64> {
+        assert!(
+            self.lo <= x && x < self.hi,
+            "{} is out of the domain {}..{}",
+            x,
+            self.lo,
+            self.hi
+        );
+
+        let mut best: Option<i64> = None;
+        let (mut node, mut start, mut end) = (Some(0), self.lo, self.hi - 1);
+        while let Some(current) = node {
+            if let Some(line) = self.nodes[current].line {
+                let y = line.eval(x);
+                best = Some(best.map_or(y, |best| best.min(y)));
+            }
+
+            let mid = start + (end - start).div_euclid(2);
+            if x <= mid {
+                node = self.nodes[current].left;
+                end = mid;
+            } else {
+                node = self.nodes[current].right;
+                start = mid + 1;
+            }
+        }
+        best.map(|y| E::sign() * y)
+    }
+
+    // --------------------- HELPERS ---------------------
+
+    fn new_node(&mut self) -> usize {
+        self.nodes.push(Node {
+            line: None,
+            left: None,
+            right: None,
+        });
+        self.nodes.len() - 1
+    }
+
+    // the left or right child of a node, created if needed
+    fn child(&mut self, node: usize, left: bool) -> usize {
+        let existing = if left {
+            self.nodes[node].left
+        } else {
+            self.nodes[node].right
+        };
+        if let Some(child) = existing {
+            return child;
+        }
+
+        let child = self.new_node();
+        if left {
+            self.nodes[node].left = Some(child);
+        } else {
+            self.nodes[node].right = Some(child);
+        }
+        child
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    // random line, restricted to a random segment half of the time
+    fn random_segment(rng: &mut Lcg, lo: i64, hi: i64) -> (Line, i64, i64) {
+        let line = Line::new(rng.next(41) as i64 - 20, rng.next(2001) as i64 - 1000);
+        if rng.next(2) == 0 {
+            return (line, i64::MIN, i64::MAX);
+        }
+        let width = (hi - lo) as usize + 1;
+        let (a, b) = (
+            lo + rng.next(width) as i64 - 1,
+            lo + rng.next(width) as i64 - 1,
+        );
+        (line, a.min(b), a.max(b))
+    }
+
+    fn brute_force(segments: &[(Line, i64, i64)], x: i64, maximize: bool) -> Option<i64> {
+        let values = segments
+            .iter()
+            .filter(|&&(_, l, r)| l <= x && x <= r)
+            .map(|(line, _, _)| line.eval(x));
+        if maximize {
+            values.max()
+        } else {
+            values.min()
+        }
+    }
+
+    #[test]
+    fn test_li_chao_tree() {
+        let mut rng = Lcg(23);
+        for n in [1, 2, 7, 50] {
+            // sparse abscissas, only the listed ones can be queried
+            let xs: Vec<i64> = (0..n).map(|i| 3 * i - 40).collect();
+            let (lo, hi) = (xs[0], xs[xs.len() - 1]);
+            let mut min_tree = LiChaoTree::<Min<i64>>::new(xs.iter().copied());
+            let mut max_tree = LiChaoTree::<Max<i64>>::new(xs.iter().copied());
+            assert_eq!(min_tree.len(), n as usize);
+
+            let mut segments = Vec::new();
+            for _ in 0..100 {
+                let (line, l, r) = random_segment(&mut rng, lo, hi);
+                if l == i64::MIN {
+                    min_tree.add_line(line);
+                    max_tree.add_line(line);
+                } else {
+                    min_tree.add_segment(line, l, r);
+                    max_tree.add_segment(line, l, r);
+                }
+                segments.push((line, l, r));
+
+                let x = xs[rng.next(xs.len())];
+                assert_eq!(min_tree.query(x), brute_force(&segments, x, false));
+                assert_eq!(max_tree.query(x), brute_force(&segments, x, true));
+            }
+        }
+
+        let mut tree = LiChaoTree::<Min<i64>>::new(0..10);
+        assert_eq!(tree.query(4), None);
+        tree.add_segment(Line::new(1, 0), 6, 20);
+        assert_eq!((tree.query(5), tree.query(9)), (None, Some(9)));
+    }
+
+    #[test]
+    fn test_dynamic_li_chao_tree() {
+        let mut rng = Lcg(29);
+        for (lo, hi) in [(0, 1), (-5, 5), (-1000, 1000)] {
+            let mut min_tree = DynamicLiChaoTree::<Min<i64>>::new(lo, hi);
+            let mut max_tree = DynamicLiChaoTree::<Max<i64>>::new(lo, hi);
+
+            let mut segments = Vec::new();
+            for _ in 0..100 {
+                let (line, l, r) = random_segment(&mut rng, lo, hi - 1);
+                if l == i64::MIN {
+                    min_tree.add_line(line);
+                    max_tree.add_line(line);
+                } else {
+                    min_tree.add_segment(line, l, r);
+                    max_tree.add_segment(line, l, r);
+                }
+                segments.push((line, l, r));
+
+                let x = lo + rng.next((hi - lo) as usize) as i64;
+                assert_eq!(min_tree.query(x), brute_force(&segments, x, false));
+                assert_eq!(max_tree.query(x), brute_force(&segments, x, true));
+            }
+        }
+
+        // a huge domain only allocates along the insertion paths
+        let mut tree = DynamicLiChaoTree::<Min<i64>>::new(-1_000_000_000, 1_000_000_000);
+        for k in 0..40 {
+            tree.add_line(Line::new(k - 20, k * k));
+        }
+        assert!(tree.node_count() <= 1 + 40 * 31);
+        let x = -999_999_999;
+        let expected = (0..40).map(|k| (k - 20) * x + k * k).min();
+        assert_eq!(tree.query(x), expected);
+    }
+
+    #[test]
+    fn test_convex_hull_trick_dp() {
+        // dp[i] = min over j < i of dp[j] + (h[i] - h[j])² + c, i.e. lines of slope -2·h[j]
+        let h = [1, 4, 5, 9, 12, 13, 20, 22];
+        let c = 10;
+
+        let mut expected = vec![0; h.len()];
+        for i in 1..h.len() {
+            expected[i] = (0..i)
+                .map(|j| expected[j] + (h[i] - h[j]) * (h[i] - h[j]) + c)
+                .min()
+                .unwrap();
+        }
+
+        let mut tree = DynamicLiChaoTree::<Min<i64>>::new(0, 100);
+        let mut dp = vec![0; h.len()];
+        tree.add_line(Line::new(-2 * h[0], h[0] * h[0]));
+        for i in 1..h.len() {
+            dp[i] = tree.query(h[i]).unwrap() + h[i] * h[i] + c;
+            tree.add_line(Line::new(-2 * h[i], dp[i] + h[i] * h[i]));
+        }
+        assert_eq!(dp, expected);
+    }
+}
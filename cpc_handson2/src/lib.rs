@@ -1,97 +1,162 @@
+use std::cmp::Ordering;
 use std::fs::File;
 use std::io::BufRead;
 use std::io::BufReader;
-
-pub struct SegmentTree {
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+
+pub mod beats;
+pub mod dynamic;
+pub mod fenwick;
+pub mod iterative;
+pub mod lazy;
+pub mod li_chao;
+pub mod max_subarray;
+pub mod monoid;
+pub mod num;
+pub mod persistent;
+pub mod range;
+pub mod segment_tree_2d;
+pub mod sparse_table;
+pub mod sweep;
+#[cfg(test)]
+mod test_util;
+pub mod wavelet;
+
+use lazy::{Add, ChMin, LazyAction, NoAction, RangeAdd, RangeAssign};
+use monoid::{Max, Min, MinCount, MinMax, Monoid};
+use num::Bounded;
+use range::{check_index, to_closed, to_half_open, RangeError};
+
+// Segment tree over the monoid `M`, with range updates described by the lazy action `A`.
+// A node value always includes the updates applied to the node, while its lazy tag holds the updates
+// that still have to be pushed down to its children.
+// Only the updates push the tags down: the reads carry the tags of the ancestors along the path and apply
+// them on the fly, so they take `&self` and a built tree can be shared between threads (e.g. in an `Arc`).
+pub struct SegmentTree<M: Monoid, A: LazyAction<M> = NoAction> {
     n: usize,
-    tree: Vec<i32>,
-    lazy: Vec<Option<i32>>,
-}
-
-pub enum NodeFunction {
-    Min,
-    Max,
+    tree: Vec<M::Value>,
+    lazy: Vec<Option<A::Tag>>,
+    action: PhantomData<A>,
 }
 
-impl SegmentTree {
+impl<M: Monoid, A: LazyAction<M>> SegmentTree<M, A> {
     // --------------------- CONSTRUCTOR ---------------------
 
-    // init the segment tree object from the given array array
-    pub fn init(a: &[i32], node_function: &NodeFunction) -> Self {
+    // init the segment tree object from the given array, each node holds the combination of its range under M
+    pub fn init(a: &[M::Value]) -> Self {
         let length = a.len();
 
         let mut segment_tree = SegmentTree {
             n: length,
-            tree: vec![0; 4 * length],
+            tree: vec![M::identity(); 4 * length],
             lazy: vec![None; 4 * length],
+            action: PhantomData,
         };
 
-        segment_tree.build(a, 0, length - 1, 0, node_function);
+        // an empty tree has no node: every range of it is empty, and every index out of bounds
+        if length > 0 {
+            segment_tree.build(a, 0, length - 1, 0);
+        }
         segment_tree
     }
 
     // fill the segment tree with the array data
-    fn build(
-        &mut self,
-        arr: &[i32],
-        start_pos: usize,
-        end_pos: usize,
-        curr_node_pos: usize,
-        node_function: &NodeFunction,
-    ) {
+    fn build(&mut self, arr: &[M::Value], start_pos: usize, end_pos: usize, curr_node_pos: usize) {
         // base case: if the start and end positions are the same, then we are at a leaf node
         if start_pos == end_pos {
-            self.tree[curr_node_pos] = arr[end_pos];
+            self.tree[curr_node_pos] = arr[end_pos].clone();
             return;
         }
 
         // split the range into two halves and populate the left and right subtrees
         let mid = (start_pos + end_pos) / 2;
 
-        self.build(
-            arr,
-            start_pos,
-            mid,
-            left_child(curr_node_pos),
-            node_function,
-        );
-        self.build(
-            arr,
-            mid + 1,
-            end_pos,
-            right_child(curr_node_pos),
-            node_function,
+        self.build(arr, start_pos, mid, left_child(curr_node_pos));
+        self.build(arr, mid + 1, end_pos, right_child(curr_node_pos));
+
+        // set the value of the current node to the combination of the left and right children
+        self.pull(curr_node_pos);
+    }
+
+    // recompute the value of a node from its children
+    fn pull(&mut self, node_pos: usize) {
+        self.tree[node_pos] = M::combine(
+            &self.tree[left_child(node_pos)],
+            &self.tree[right_child(node_pos)],
         );
+    }
 
-        // set the value of the current node to the max or min of the left and right children, depending on the given problem
-        self.tree[curr_node_pos] = match node_function {
-            NodeFunction::Min => {
-                self.tree[left_child(curr_node_pos)].min(self.tree[right_child(curr_node_pos)])
-            }
-            NodeFunction::Max => {
-                self.tree[left_child(curr_node_pos)].max(self.tree[right_child(curr_node_pos)])
-            }
-        };
+    // --------------------- LAZY TAGS ---------------------
+
+    // apply a tag to the node covering [start, end], and remember it for the children if it is not a leaf
+    fn apply_tag(&mut self, node_pos: usize, start: usize, end: usize, tag: &A::Tag) {
+        self.tree[node_pos] = A::apply(tag, &self.tree[node_pos], end - start + 1);
+
+        if start != end {
+            self.lazy[node_pos] = Some(match &self.lazy[node_pos] {
+                // the node already has a pending tag, the new one comes after it
+                Some(old_tag) => A::compose(tag, old_tag),
+                None => tag.clone(),
+            });
+        }
     }
 
-    // --------------------- MAX QUERY ---------------------
+    // push the pending tag of a node down to its children
+    fn push_down(&mut self, node_pos: usize, start: usize, end: usize) {
+        if let Some(tag) = self.lazy[node_pos].take() {
+            let mid = (start + end) / 2;
+            self.apply_tag(left_child(node_pos), start, mid, &tag);
+            self.apply_tag(right_child(node_pos), mid + 1, end, &tag);
+        }
 
-    // max query in range [l, r]
-    pub fn max_query(&mut self, l: usize, r: usize) -> Option<i32> {
-        self.max_query_rec(0, 0, self.n - 1, l - 1, r - 1)
+        // otherwise, there is no lazy update to do
     }
 
-    fn max_query_rec(
-        &mut self,
+    // value of a node with the tags still pending above it applied, without pushing them down
+    fn value_with(
+        &self,
+        node_pos: usize,
+        start: usize,
+        end: usize,
+        pending: &Option<A::Tag>,
+    ) -> M::Value {
+        match pending {
+            Some(tag) => A::apply(tag, &self.tree[node_pos], end - start + 1),
+            None => self.tree[node_pos].clone(),
+        }
+    }
+
+    // tags still pending above the children of a node: its own tag, then the newer ones of its ancestors
+    fn pending_for_children(&self, node_pos: usize, pending: &Option<A::Tag>) -> Option<A::Tag> {
+        match (pending, &self.lazy[node_pos]) {
+            (Some(outer), Some(inner)) => Some(A::compose(outer, inner)),
+            (Some(tag), None) | (None, Some(tag)) => Some(tag.clone()),
+            (None, None) => None,
+        }
+    }
+
+    // --------------------- QUERY ---------------------
+
+    // combination of the values in `range` (the identity for an empty range)
+    pub fn query(&self, range: impl RangeBounds<usize>) -> Result<M::Value, RangeError> {
+        Ok(match to_closed(range, self.n)? {
+            Some((l, r)) => self
+                .query_rec(0, 0, self.n - 1, l, r, &None)
+                .unwrap_or_else(M::identity),
+            None => M::identity(),
+        })
+    }
+
+    fn query_rec(
+        &self,
         curr_node_pos: usize,
         start: usize,
         end: usize,
         l: usize,
         r: usize,
-    ) -> Option<i32> {
-        // make sure our tree is up-to-date by applying any lazy updates at the start
-        self.apply_lazy_update(curr_node_pos, start, end);
-
+        pending: &Option<A::Tag>,
+    ) -> Option<M::Value> {
         if start > r || end < l {
             // no overlap, skip node
             return None;
@@ -99,163 +164,614 @@ impl SegmentTree {
 
         if start >= l && end <= r {
             // total overlap, return the value of the current node
-            return Some(self.tree[curr_node_pos]);
+            return Some(self.value_with(curr_node_pos, start, end, pending));
         }
 
-        // partial overlap, recurse on the children
+        // partial overlap, recurse on the children with the tags they have not received yet
+        let pending = self.pending_for_children(curr_node_pos, pending);
         let mid = (start + end) / 2;
 
-        let left_max = self.max_query_rec(left_child(curr_node_pos), start, mid, l, r);
-        let right_max = self.max_query_rec(right_child(curr_node_pos), mid + 1, end, l, r);
+        let left_value = self.query_rec(left_child(curr_node_pos), start, mid, l, r, &pending);
+        let right_value = self.query_rec(right_child(curr_node_pos), mid + 1, end, l, r, &pending);
 
-        match (left_max, right_max) {
-            // return the max of the two values
-            (Some(left_max), Some(right_max)) => Some(left_max.max(right_max)),
+        match (left_value, right_value) {
+            // return the combination of the two values
+            (Some(left_value), Some(right_value)) => Some(M::combine(&left_value, &right_value)),
 
             // return the value that is not None
-            (Some(left_max), None) => Some(left_max),
-            (None, Some(right_max)) => Some(right_max),
+            (Some(left_value), None) => Some(left_value),
+            (None, Some(right_value)) => Some(right_value),
 
-            // return None if both values are None
-            (None, None) => None, // TODO check if this is correct
-        }
-    }
-
-    // apply the lazy update to a node
-    fn apply_lazy_update(&mut self, node_pos: usize, start: usize, end: usize) {
-        if let Some(lazy_update_value) = self.lazy[node_pos] {
-            // update the current node
-            self.tree[node_pos] = self.tree[node_pos].min(lazy_update_value);
-
-            // propagate the lazy value to the node's children if its not a leaf node
-            if start != end {
-                self.lazy_min_or_set(left_child(node_pos), lazy_update_value);
-                self.lazy_min_or_set(right_child(node_pos), lazy_update_value);
-            }
-
-            // clear the lazy value for this node
-            self.lazy[node_pos] = None;
-        }
-
-        // otherwise, there is no lazy update to do
-    }
-
-    // set the lazy value for a node by taking the min between the current lazy value and the new lazy value
-    fn lazy_min_or_set(&mut self, node_pos: usize, new_lazy_value: i32) {
-        if let Some(old_lazy_value) = self.lazy[node_pos] {
-            // the lazy tree has a already value for this node, choose the min between this and the new lazy value
-            self.lazy[node_pos] = Some(old_lazy_value.min(new_lazy_value));
-        } else {
-            // the lazy tree has no value for this node, set the new lazy value
-            self.lazy[node_pos] = Some(new_lazy_value);
+            // no overlap on both sides
+            (None, None) => None,
         }
     }
 
     // ---------------------- RANGE UPDATE ----------------------
 
-    pub fn update_range(&mut self, l: usize, r: usize, t: i32) {
-        self.update_range_rec(0, 0, self.n - 1, l - 1, r - 1, t);
+    // apply the update described by `tag` to every position in `range`
+    pub fn apply_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        tag: A::Tag,
+    ) -> Result<(), RangeError> {
+        if let Some((l, r)) = to_closed(range, self.n)? {
+            self.apply_range_rec(0, 0, self.n - 1, l, r, &tag);
+        }
+        Ok(())
     }
 
-    fn update_range_rec(
+    fn apply_range_rec(
         &mut self,
         curr_node_pos: usize,
         start: usize,
         end: usize,
         l: usize,
         r: usize,
-        t: i32,
+        tag: &A::Tag,
     ) {
-        // make sure our tree is up-to-date by applying any lazy updates at the start
-        self.apply_lazy_update(curr_node_pos, start, end);
-
         if start > r || end < l {
             // no overlap, skip
             return;
         }
 
         if start >= l && end <= r {
-            // total overlap
-            // update the current node
-            self.tree[curr_node_pos] = self.tree[curr_node_pos].min(t);
-
-            // if not a leaf, propagate a lazy update to children
-            if start != end {
-                self.lazy_min_or_set(left_child(curr_node_pos), t);
-                self.lazy_min_or_set(right_child(curr_node_pos), t);
-            }
+            // total overlap, update the current node and leave the update pending for its children
+            self.apply_tag(curr_node_pos, start, end, tag);
         } else {
             // partial overlap, recurse on the children
+            self.push_down(curr_node_pos, start, end);
             let mid = (start + end) / 2;
 
-            self.update_range_rec(left_child(curr_node_pos), start, mid, l, r, t);
-            self.update_range_rec(right_child(curr_node_pos), mid + 1, end, l, r, t);
+            self.apply_range_rec(left_child(curr_node_pos), start, mid, l, r, tag);
+            self.apply_range_rec(right_child(curr_node_pos), mid + 1, end, l, r, tag);
 
-            // update the current node to the max of the children
-            self.tree[curr_node_pos] =
-                self.tree[left_child(curr_node_pos)].max(self.tree[right_child(curr_node_pos)]);
+            // update the current node from its children
+            self.pull(curr_node_pos);
         }
     }
 
-    // ---------------------- PROBLEM 2 ----------------------
-    // ---------------------- IS THERE ----------------------
+    // ---------------------- POINT ACCESS ----------------------
 
-    // Check if there exists a position within range [l, r] with exactly 'k' segments covering it
-    pub fn exists_exact_coverage(&mut self, l: usize, r: usize, k: i32) -> bool {
-        self.range_exact_check(0, 0, self.n - 1, l, r, k)
+    // a[i] = value, any pending update on position i is overwritten
+    pub fn set(&mut self, i: usize, value: M::Value) -> Result<(), RangeError> {
+        check_index(i, self.n)?;
+        self.set_rec(0, 0, self.n - 1, i, value);
+        Ok(())
     }
 
-    fn range_exact_check(
+    fn set_rec(
         &mut self,
         curr_node_pos: usize,
         start: usize,
         end: usize,
-        l: usize,
-        r: usize,
-        k: i32,
-    ) -> bool {
-        // apply any pending lazy updates
-        self.apply_lazy_update(curr_node_pos, start, end);
+        i: usize,
+        value: M::Value,
+    ) {
+        if start == end {
+            self.tree[curr_node_pos] = value;
+            return;
+        }
 
-        // no overlap
-        if start > r || end < l {
-            return false;
+        // bring the pending updates down the path, then fix the nodes on the way back up
+        self.push_down(curr_node_pos, start, end);
+        let mid = (start + end) / 2;
+
+        if i <= mid {
+            self.set_rec(left_child(curr_node_pos), start, mid, i, value);
+        } else {
+            self.set_rec(right_child(curr_node_pos), mid + 1, end, i, value);
         }
 
-        // total overlap
-        if start >= l && end <= r {
-            // return self.tree[curr_node_pos] == k;
-            return self.find_first_match(curr_node_pos, k).is_some();
+        self.pull(curr_node_pos);
+    }
+
+    // current value of a[i], with all the pending updates applied
+    pub fn get(&self, i: usize) -> Result<M::Value, RangeError> {
+        check_index(i, self.n)?;
+        let (mut curr_node_pos, mut start, mut end) = (0, 0, self.n - 1);
+        let mut pending = None;
+
+        while start != end {
+            pending = self.pending_for_children(curr_node_pos, &pending);
+            let mid = (start + end) / 2;
+
+            if i <= mid {
+                curr_node_pos = left_child(curr_node_pos);
+                end = mid;
+            } else {
+                curr_node_pos = right_child(curr_node_pos);
+                start = mid + 1;
+            }
+        }
+
+        Ok(self.value_with(curr_node_pos, start, end, &pending))
+    }
+
+    // the whole current array, with all the pending updates applied
+    pub fn to_vec(&self) -> Vec<M::Value> {
+        let mut values = Vec::with_capacity(self.n);
+        if self.n > 0 {
+            self.collect_rec(0, 0, self.n - 1, &None, &mut values);
+        }
+        values
+    }
+
+    fn collect_rec(
+        &self,
+        curr_node_pos: usize,
+        start: usize,
+        end: usize,
+        pending: &Option<A::Tag>,
+        values: &mut Vec<M::Value>,
+    ) {
+        if start == end {
+            values.push(self.value_with(curr_node_pos, start, end, pending));
+            return;
         }
 
-        // Partial overlap, recurse on the children
+        let pending = self.pending_for_children(curr_node_pos, pending);
         let mid = (start + end) / 2;
-        let left_exists = self.range_exact_check(left_child(curr_node_pos), start, mid, l, r, k);
-        let right_exists =
-            self.range_exact_check(right_child(curr_node_pos), mid + 1, end, l, r, k);
+        self.collect_rec(left_child(curr_node_pos), start, mid, &pending, values);
+        self.collect_rec(right_child(curr_node_pos), mid + 1, end, &pending, values);
+    }
 
-        // return true if either the left or right child has a match
-        left_exists || right_exists
+    // ---------------------- SEARCH ----------------------
+
+    // largest r such that pred(combination of a[l..r]) holds, with l <= r <= n.
+    // pred must hold on the identity and be monotone: once false, it stays false when the range grows
+    pub fn max_right<F>(&self, l: usize, pred: F) -> Result<usize, RangeError>
+    where
+        F: Fn(&M::Value) -> bool,
+    {
+        // l is a border between positions, like r in min_left: past n it is out of bounds
+        if l > self.n {
+            return Err(RangeError::OutOfBounds {
+                end: l,
+                len: self.n,
+            });
+        }
+        if l == self.n {
+            return Ok(self.n);
+        }
+
+        let mut acc = M::identity();
+        Ok(self
+            .max_right_rec(0, 0, self.n - 1, l, &None, &pred, &mut acc)
+            .unwrap_or(self.n))
     }
 
-    // helper function to find the first match of a value in the st
-    fn find_first_match(&mut self, node: usize, value: i32) -> Option<i32> {
-        // Base case: if the current node is out of bounds or the node value is greater than the search value, return None
-        if node >= self.tree.len() || self.tree[node] > value {
+    // returns the first position where pred fails, `acc` holds the combination of the positions accepted so far
+    #[allow(clippy::too_many_arguments)]
+    fn max_right_rec<F>(
+        &self,
+        curr_node_pos: usize,
+        start: usize,
+        end: usize,
+        l: usize,
+        pending: &Option<A::Tag>,
+        pred: &F,
+        acc: &mut M::Value,
+    ) -> Option<usize>
+    where
+        F: Fn(&M::Value) -> bool,
+    {
+        if end < l {
+            // the node is before the start of the range
             return None;
         }
 
-        // If the current node holds an exact match, return it
-        if self.tree[node] == value {
-            return Some(self.tree[node]);
+        if start >= l {
+            // the whole node is in the range, take it if pred still holds
+            let combined = M::combine(acc, &self.value_with(curr_node_pos, start, end, pending));
+            if pred(&combined) {
+                *acc = combined;
+                return None;
+            }
+            if start == end {
+                return Some(start);
+            }
+        }
+
+        // the answer is inside this node, look at the children from left to right
+        let pending = self.pending_for_children(curr_node_pos, pending);
+        let mid = (start + end) / 2;
+
+        self.max_right_rec(
+            left_child(curr_node_pos),
+            start,
+            mid,
+            l,
+            &pending,
+            pred,
+            acc,
+        )
+        .or_else(|| {
+            self.max_right_rec(
+                right_child(curr_node_pos),
+                mid + 1,
+                end,
+                l,
+                &pending,
+                pred,
+                acc,
+            )
+        })
+    }
+
+    // smallest l such that pred(combination of a[l..r]) holds, with 0 <= l <= r.
+    // pred must hold on the identity and be monotone: once false, it stays false when the range grows
+    pub fn min_left<F>(&self, r: usize, pred: F) -> Result<usize, RangeError>
+    where
+        F: Fn(&M::Value) -> bool,
+    {
+        let (_, r) = to_half_open(..r, self.n)?;
+        if r == 0 {
+            return Ok(0);
+        }
+
+        let mut acc = M::identity();
+        Ok(self
+            .min_left_rec(0, 0, self.n - 1, r, &None, &pred, &mut acc)
+            .unwrap_or(0))
+    }
+
+    // returns one past the last position (from the right) where pred fails
+    #[allow(clippy::too_many_arguments)]
+    fn min_left_rec<F>(
+        &self,
+        curr_node_pos: usize,
+        start: usize,
+        end: usize,
+        r: usize,
+        pending: &Option<A::Tag>,
+        pred: &F,
+        acc: &mut M::Value,
+    ) -> Option<usize>
+    where
+        F: Fn(&M::Value) -> bool,
+    {
+        if start >= r {
+            // the node is after the end of the range
+            return None;
+        }
+
+        if end < r {
+            // the whole node is in the range, take it if pred still holds
+            let combined = M::combine(&self.value_with(curr_node_pos, start, end, pending), acc);
+            if pred(&combined) {
+                *acc = combined;
+                return None;
+            }
+            if start == end {
+                return Some(start + 1);
+            }
+        }
+
+        // the answer is inside this node, look at the children from right to left
+        let pending = self.pending_for_children(curr_node_pos, pending);
+        let mid = (start + end) / 2;
+
+        self.min_left_rec(
+            right_child(curr_node_pos),
+            mid + 1,
+            end,
+            r,
+            &pending,
+            pred,
+            acc,
+        )
+        .or_else(|| {
+            self.min_left_rec(
+                left_child(curr_node_pos),
+                start,
+                mid,
+                r,
+                &pending,
+                pred,
+                acc,
+            )
+        })
+    }
+
+    // first position i in `range` such that matches(a[i]) holds.
+    // The descent skips every node where may_contain fails on the combination of its range, so
+    // may_contain must hold on a node whenever matches holds on one of its positions: to find a
+    // value equal to k, use `|m| *m <= k` and `|v| *v == k` on a Min tree, `|m| *m >= k` and
+    // `|v| *v == k` on a Max tree. The same predicate can be passed twice when it is already
+    // monotone that way (e.g. `|m| *m <= k` on a Min tree).
+    pub fn find_first<F, G>(
+        &self,
+        range: impl RangeBounds<usize>,
+        may_contain: F,
+        matches: G,
+    ) -> Result<Option<usize>, RangeError>
+    where
+        F: Fn(&M::Value) -> bool,
+        G: Fn(&M::Value) -> bool,
+    {
+        let Some((l, r)) = to_closed(range, self.n)? else {
+            return Ok(None);
+        };
+
+        // nodes still to visit, the right child is pushed first so that the left one comes out first
+        let mut to_visit = vec![(0, 0, self.n - 1, None)];
+        while let Some((curr_node_pos, start, end, pending)) = to_visit.pop() {
+            if start > r || end < l {
+                // no overlap
+                continue;
+            }
+
+            let value = self.value_with(curr_node_pos, start, end, &pending);
+            if !may_contain(&value) {
+                // no position of the node can match
+                continue;
+            }
+
+            if start == end {
+                if matches(&value) {
+                    return Ok(Some(start));
+                }
+                continue;
+            }
+
+            let pending = self.pending_for_children(curr_node_pos, &pending);
+            let mid = (start + end) / 2;
+            to_visit.push((right_child(curr_node_pos), mid + 1, end, pending.clone()));
+            to_visit.push((left_child(curr_node_pos), start, mid, pending));
+        }
+
+        Ok(None)
+    }
+}
+
+impl<M: Monoid, A: RangeAdd<M>> SegmentTree<M, A> {
+    // a[i] = a[i] + delta for every i in `range`
+    pub fn add_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        delta: M::Value,
+    ) -> Result<(), RangeError> {
+        self.apply_range(range, A::add_tag(delta))
+    }
+}
+
+impl<M: Monoid, A: RangeAssign<M>> SegmentTree<M, A> {
+    // a[i] = value for every i in `range`
+    pub fn assign_range(
+        &mut self,
+        range: impl RangeBounds<usize>,
+        value: M::Value,
+    ) -> Result<(), RangeError> {
+        self.apply_range(range, A::assign_tag(value))
+    }
+}
+
+impl<M: Monoid, T> SegmentTree<M, ChMin<T>>
+where
+    ChMin<T>: LazyAction<M, Tag = T>,
+{
+    // a[i] = min(a[i], t) for every i in `range`
+    pub fn update_range(&mut self, range: impl RangeBounds<usize>, t: T) -> Result<(), RangeError> {
+        self.apply_range(range, t)
+    }
+}
+
+impl<T: Bounded, A: LazyAction<Max<T>>> SegmentTree<Max<T>, A> {
+    // --------------------- MAX QUERY ---------------------
+
+    // max query in `range` (`T::min_value()` for an empty range)
+    pub fn max_query(&self, range: impl RangeBounds<usize>) -> Result<T, RangeError> {
+        self.query(range)
+    }
+}
+
+impl<A: LazyAction<Min<i32>>> SegmentTree<Min<i32>, A> {
+    // ---------------------- PROBLEM 2 ----------------------
+    // ---------------------- IS THERE ----------------------
+
+    // Check if there exists a position within `range` with exactly 'k' segments covering it
+    pub fn exists_exact_coverage(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: i32,
+    ) -> Result<bool, RangeError> {
+        Ok(self.exact_coverage_position(range, k)?.is_some())
+    }
+
+    // first position within `range` with exactly 'k' segments covering it, if any
+    pub fn exact_coverage_position(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: i32,
+    ) -> Result<Option<usize>, RangeError> {
+        // a node can only hold a position equal to k if its minimum is at most k
+        let may_contain = |m: &i32| *m <= k;
+        let matches = |m: &i32| *m == k;
+
+        self.find_first(range, may_contain, matches)
+    }
+}
+
+impl SegmentTree<MinMax<i32>, Add<i32>> {
+    // ---------------------- DYNAMIC COVERAGE ----------------------
+    // Each position holds how many segments cover it, as a (min, max) pair so that the search for
+    // an exact coverage can skip the nodes where k is out of [min, max].
+
+    // coverage tree over n positions, with no segment yet
+    pub fn coverage(n: usize) -> Self {
+        Self::init(&vec![(0, 0); n])
+    }
+
+    // coverage tree over n positions, starting with the given segments (closed, 0-based);
+    // fails with the error `add_segment` would return on the first segment that does not fit
+    pub fn coverage_from_segments(
+        n: usize,
+        segments: &[(usize, usize)],
+    ) -> Result<Self, RangeError> {
+        let freq = build_frequency_array(n, segments)?;
+        Ok(Self::init(
+            &freq.iter().map(|&c| (c, c)).collect::<Vec<_>>(),
+        ))
+    }
+
+    // one more segment covers the positions in `range`
+    pub fn add_segment(&mut self, range: impl RangeBounds<usize>) -> Result<(), RangeError> {
+        self.apply_range(range, 1)
+    }
+
+    // remove a segment previously added on `range`
+    pub fn remove_segment(&mut self, range: impl RangeBounds<usize>) -> Result<(), RangeError> {
+        self.apply_range(range, -1)
+    }
+
+    // Check if there exists a position within `range` with exactly 'k' segments covering it
+    pub fn exists_exact_coverage(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: i32,
+    ) -> Result<bool, RangeError> {
+        Ok(self.exact_coverage_position(range, k)?.is_some())
+    }
+
+    // first position within `range` with exactly 'k' segments covering it, if any
+    pub fn exact_coverage_position(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: i32,
+    ) -> Result<Option<usize>, RangeError> {
+        // a node can only hold k if k is in [min, max], and on a leaf min == max
+        self.find_first(
+            range,
+            |&(min, max)| min <= k && k <= max,
+            |&(min, _)| min == k,
+        )
+    }
+}
+
+impl SegmentTree<MinCount<i32>, Add<i32>> {
+    // ---------------------- COVERAGE COUNTS ----------------------
+    // Each position holds how many segments cover it, the nodes keep the minimum coverage of their range
+    // together with how many positions reach it. Counting the positions at the minimum (e.g. the
+    // uncovered ones) is a plain O(log n) query, the other counts have to go down into every node whose
+    // minimum is below k, and may visit all the leaves of the range.
+
+    // coverage tree over n positions, starting with the given segments (closed, 0-based);
+    // fails with the error `add_segment` would return on the first segment that does not fit
+    pub fn coverage_counts_from_segments(
+        n: usize,
+        segments: &[(usize, usize)],
+    ) -> Result<Self, RangeError> {
+        let freq = build_frequency_array(n, segments)?;
+        Ok(Self::init(
+            &freq.iter().map(|&c| (c, 1)).collect::<Vec<_>>(),
+        ))
+    }
+
+    // one more segment covers the positions in `range`
+    pub fn add_segment(&mut self, range: impl RangeBounds<usize>) -> Result<(), RangeError> {
+        self.apply_range(range, 1)
+    }
+
+    // remove a segment previously added on `range`
+    pub fn remove_segment(&mut self, range: impl RangeBounds<usize>) -> Result<(), RangeError> {
+        self.apply_range(range, -1)
+    }
+
+    // number of positions within `range` covered by no segment
+    pub fn count_zero(&self, range: impl RangeBounds<usize>) -> Result<usize, RangeError> {
+        let (min, count) = self.query(range)?;
+        Ok(if min == 0 { count } else { 0 })
+    }
+
+    // number of positions within `range` covered exactly 'k' times, in O(log n) when no position of
+    // the range is below k but O(n) in the worst case (every node whose minimum is below k is opened)
+    pub fn count_exact(&self, range: impl RangeBounds<usize>, k: i32) -> Result<usize, RangeError> {
+        // a node whose minimum is k holds exactly `count` positions equal to k
+        self.count(range, |&(min, count), len| match min.cmp(&k) {
+            Ordering::Greater => Some(0),
+            Ordering::Equal => Some(count),
+            Ordering::Less => (len == 1).then_some(0),
+        })
+    }
+
+    // number of positions within `range` covered at least 'k' times, in O(log n) when no position of
+    // the range is below k but O(n) in the worst case (every node whose minimum is below k is opened)
+    pub fn count_at_least(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: i32,
+    ) -> Result<usize, RangeError> {
+        // count the positions below k, and take them out of the range
+        let (start, end) = to_half_open(range, self.n)?;
+        let below = self.count(start..end, |&(min, _), len| {
+            if min >= k {
+                Some(0)
+            } else {
+                (len == 1).then_some(1)
+            }
+        })?;
+        Ok(end - start - below)
+    }
+
+    // sum of `decide` over the nodes covering `range`: on a node of length `len`, `decide` returns
+    // the count of the node or None to look at its children (it must decide on the leaves)
+    fn count<F>(&self, range: impl RangeBounds<usize>, decide: F) -> Result<usize, RangeError>
+    where
+        F: Fn(&(i32, usize), usize) -> Option<usize>,
+    {
+        Ok(match to_closed(range, self.n)? {
+            Some((l, r)) => self.count_rec(0, 0, self.n - 1, l, r, &None, &decide),
+            None => 0,
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn count_rec<F>(
+        &self,
+        curr_node_pos: usize,
+        start: usize,
+        end: usize,
+        l: usize,
+        r: usize,
+        pending: &Option<i32>,
+        decide: &F,
+    ) -> usize
+    where
+        F: Fn(&(i32, usize), usize) -> Option<usize>,
+    {
+        if start > r || end < l {
+            // no overlap
+            return 0;
+        }
+
+        if start >= l && end <= r {
+            let value = self.value_with(curr_node_pos, start, end, pending);
+            if let Some(count) = decide(&value, end - start + 1) {
+                return count;
+            }
         }
 
-        // Otherwise, search the left and right children recursively
-        let left_match = self.find_first_match(left_child(node), value);
-        let right_match = self.find_first_match(right_child(node), value);
+        let pending = self.pending_for_children(curr_node_pos, pending);
+        let mid = (start + end) / 2;
 
-        // If a match is found in the left subtree, return it; otherwise, return the right subtree's result
-        left_match.or(right_match)
+        self.count_rec(
+            left_child(curr_node_pos),
+            start,
+            mid,
+            l,
+            r,
+            &pending,
+            decide,
+        ) + self.count_rec(
+            right_child(curr_node_pos),
+            mid + 1,
+            end,
+            l,
+            r,
+            &pending,
+            decide,
+        )
     }
 }
 
@@ -268,17 +784,20 @@ pub fn right_child(index: usize) -> usize {
     index * 2 + 2
 }
 
-// Builds a frequency array to track the coverage of positions based on the input segments
-fn build_frequency_array(n: usize, segments_array: &[(usize, usize)]) -> Vec<i32> {
+// Builds a frequency array to track the coverage of positions based on the input segments,
+// checking each segment as a range of 0..n
+fn build_frequency_array(
+    n: usize,
+    segments_array: &[(usize, usize)],
+) -> Result<Vec<i32>, RangeError> {
     // freq[x] = how many segments cover the position x
     let mut freq = vec![0; n + 1];
 
     // for each segment (l, r), increment freq[l] and decrement freq[r+1] (using a differential array technique)
     for &(l, r) in segments_array {
-        freq[l] += 1;
-        if r + 1 < n {
-            freq[r + 1] -= 1;
-        }
+        let (start, end) = to_half_open(l..=r, n)?;
+        freq[start] += 1;
+        freq[end] -= 1;
     }
 
     // Accumulate to get coverage at each position
@@ -287,7 +806,7 @@ fn build_frequency_array(n: usize, segments_array: &[(usize, usize)]) -> Vec<i32
     }
 
     freq.pop(); // Remove extra element due to (n+1) initialization
-    freq
+    Ok(freq)
 }
 
 // data structure to help us manupulate and manage the files
@@ -330,15 +849,16 @@ pub fn problem1() {
         println!("Expected Outputs: {:?}", expected_outputs);
 
         // create a segment tree with the given array
-        let node_function = NodeFunction::Max;
-        let mut segment_tree = SegmentTree::init(array, &node_function);
+        let mut segment_tree = SegmentTree::<Max<i32>, ChMin<i32>>::init(array);
 
         // apply the queries to the segment tree
         let mut results = Vec::new();
         for query in queries {
+            // the queries in the files are 1-based
+            let range = query.0 - 1..query.1;
             match query.2 {
-                Some(value) => segment_tree.update_range(query.0, query.1, value),
-                None => results.push(segment_tree.max_query(query.0, query.1).unwrap()),
+                Some(value) => segment_tree.update_range(range, value).unwrap(),
+                None => results.push(segment_tree.max_query(range).unwrap()),
             };
         }
 
@@ -379,16 +899,17 @@ pub fn problem2() {
             .iter()
             .map(|&(x, y)| (x as usize, y as usize))
             .collect();
-        let freq = build_frequency_array(array.len(), &data_usize);
+        let freq = build_frequency_array(array.len(), &data_usize).unwrap();
 
         // create a segment tree with the frequency array
-        let node_function = NodeFunction::Min;
-        let mut segment_tree = SegmentTree::init(&freq, &node_function);
+        let segment_tree = SegmentTree::<Min<i32>>::init(&freq);
 
         // apply the queries to the segment tree
         let mut results = Vec::new();
         for &(i, j, k) in queries {
-            let exists = segment_tree.exists_exact_coverage(i, j, k.unwrap());
+            let exists = segment_tree
+                .exists_exact_coverage(i..=j, k.unwrap())
+                .unwrap();
             results.push(if exists { 1 } else { 0 });
         }
 
@@ -525,3 +1046,462 @@ pub fn main() {
     println!("\n");
     problem2();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lazy::{AddAssign, Assign};
+    use monoid::{Gcd, ProductMod, Sum, Xor};
+    use std::ops::Bound;
+    use test_util::Lcg;
+
+    #[test]
+    fn test_problem1() {
+        problem1();
+    }
+
+    #[test]
+    fn test_problem2() {
+        problem2();
+    }
+
+    #[test]
+    fn test_builtin_monoids() {
+        let a = [5, 3, 8, 6, 1, 4];
+
+        let min_tree = SegmentTree::<Min>::init(&a);
+        let max_tree = SegmentTree::<Max>::init(&a);
+        assert_eq!(min_tree.query(1..=3), Ok(3));
+        assert_eq!(max_tree.max_query(1..=3), Ok(8));
+
+        let sum_tree = SegmentTree::<Sum>::init(&[5, 3, 8, 6, 1, 4]);
+        assert_eq!(sum_tree.query(..), Ok(27));
+        assert_eq!(sum_tree.query(2..5), Ok(15));
+        assert_eq!(sum_tree.query(3..3), Ok(0));
+
+        let gcd_tree = SegmentTree::<Gcd>::init(&[12, 18, 24, 7, 14]);
+        assert_eq!(gcd_tree.query(..3), Ok(6));
+        assert_eq!(gcd_tree.query(3..), Ok(7));
+        assert_eq!(gcd_tree.query(2..=3), Ok(1));
+
+        let xor_tree = SegmentTree::<Xor>::init(&[1, 2, 4, 8]);
+        assert_eq!(xor_tree.query(..), Ok(15));
+        assert_eq!(xor_tree.query(1..=2), Ok(6));
+
+        let product_tree = SegmentTree::<ProductMod<7>>::init(&[3, 5, 6, 2]);
+        assert_eq!(product_tree.query(..2), Ok(1)); // 15 mod 7
+        assert_eq!(product_tree.query(..), Ok(5)); // 180 mod 7
+    }
+
+    // custom node type: the range length together with its sum, to get averages
+    #[derive(Clone, Debug, PartialEq)]
+    struct Average {
+        len: u32,
+        sum: i64,
+    }
+
+    struct AverageMonoid;
+
+    impl Monoid for AverageMonoid {
+        type Value = Average;
+
+        fn identity() -> Average {
+            Average { len: 0, sum: 0 }
+        }
+
+        fn combine(a: &Average, b: &Average) -> Average {
+            Average {
+                len: a.len + b.len,
+                sum: a.sum + b.sum,
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_monoid() {
+        let a: Vec<Average> = [4, 8, 15, 16, 23, 42]
+            .iter()
+            .map(|&x| Average { len: 1, sum: x })
+            .collect();
+
+        let tree = SegmentTree::<AverageMonoid>::init(&a);
+        assert_eq!(tree.query(..), Ok(Average { len: 6, sum: 108 }));
+        assert_eq!(tree.query(2..4), Ok(Average { len: 2, sum: 31 }));
+    }
+
+    #[test]
+    fn test_update_range_on_min_tree() {
+        // the chmin update keeps the children combined with the tree's own monoid
+        let mut tree = SegmentTree::<Min, ChMin>::init(&[5, 3, 8, 6, 1, 4]);
+        tree.update_range(2..4, 2).unwrap();
+        assert_eq!(tree.query(2..4), Ok(2));
+        assert_eq!(tree.query(..2), Ok(3));
+        assert_eq!(tree.query(..), Ok(1));
+    }
+
+    #[test]
+    fn test_point_access_under_pending_tags() {
+        let mut a: Vec<i64> = vec![4, 1, 7, 3, 9, 2, 8];
+        let mut tree = SegmentTree::<Sum, AddAssign>::init(&a);
+
+        // pending tags on whole ranges, then point operations inside them
+        tree.add_range(.., 10).unwrap();
+        tree.assign_range(2..6, 5).unwrap();
+        tree.set(3, -1).unwrap();
+        tree.add_range(1..4, 2).unwrap();
+        for v in a.iter_mut() {
+            *v += 10;
+        }
+        a[2..6].iter_mut().for_each(|v| *v = 5);
+        a[3] = -1;
+        a[1..4].iter_mut().for_each(|v| *v += 2);
+
+        for (i, &value) in a.iter().enumerate() {
+            assert_eq!(tree.get(i), Ok(value));
+        }
+        assert_eq!(tree.to_vec(), a);
+        assert_eq!(tree.query(..), Ok(a.iter().sum()));
+
+        // the tree is still consistent after being fully materialised
+        tree.assign_range(.., 0).unwrap();
+        tree.set(6, 3).unwrap();
+        assert_eq!(tree.to_vec(), vec![0, 0, 0, 0, 0, 0, 3]);
+        assert_eq!(tree.query(4..), Ok(3));
+    }
+
+    #[test]
+    fn test_add_and_assign_on_min_max_trees() {
+        let mut a: Vec<i64> = vec![6, -2, 5, 0, 3];
+        let mut min_tree = SegmentTree::<Min, AddAssign>::init(&a);
+        let mut max_tree = SegmentTree::<Max, AddAssign>::init(&a);
+
+        for (range, assign, add) in [(1..4, Some(1), 0), (0..3, None, 4), (2..5, Some(-3), 2)] {
+            if let Some(value) = assign {
+                min_tree.assign_range(range.clone(), value).unwrap();
+                max_tree.assign_range(range.clone(), value).unwrap();
+                a[range.clone()].iter_mut().for_each(|v| *v = value);
+            }
+            min_tree.add_range(range.clone(), add).unwrap();
+            max_tree.add_range(range.clone(), add).unwrap();
+            a[range].iter_mut().for_each(|v| *v += add);
+
+            for l in 0..a.len() {
+                for r in l..a.len() {
+                    assert_eq!(min_tree.query(l..=r).ok(), a[l..=r].iter().min().copied());
+                    assert_eq!(max_tree.query(l..=r).ok(), a[l..=r].iter().max().copied());
+                }
+            }
+        }
+
+        // plain add and assign actions provide the same methods
+        let mut add_tree = SegmentTree::<Sum, Add>::init(&[1, 2, 3]);
+        add_tree.add_range(..2, 5).unwrap();
+        assert_eq!(add_tree.to_vec(), vec![6, 7, 3]);
+
+        let mut assign_tree = SegmentTree::<Sum, Assign>::init(&[1, 2, 3]);
+        assign_tree.assign_range(1.., 0).unwrap();
+        assign_tree.set(0, 9).unwrap();
+        assert_eq!(assign_tree.to_vec(), vec![9, 0, 0]);
+    }
+
+    #[test]
+    fn test_range_errors() {
+        let mut tree = SegmentTree::<Max, ChMin>::init(&[5, 3, 8, 6]);
+
+        assert_eq!(
+            tree.max_query(0..=4),
+            Err(RangeError::OutOfBounds { end: 5, len: 4 })
+        );
+        assert_eq!(
+            tree.update_range((Bound::Included(3), Bound::Excluded(2)), 1),
+            Err(RangeError::Decreasing { start: 3, end: 2 })
+        );
+        assert_eq!(
+            tree.get(4),
+            Err(RangeError::IndexOutOfBounds { index: 4, len: 4 })
+        );
+
+        // a failed update leaves the tree untouched
+        assert_eq!(tree.to_vec(), vec![5, 3, 8, 6]);
+
+        // an empty range is valid: the query returns the identity, the update does nothing
+        assert_eq!(tree.max_query(2..2), Ok(i64::MIN));
+        assert_eq!(tree.update_range(4.., 0), Ok(()));
+        assert_eq!(tree.max_query(..), Ok(8));
+
+        // same on an empty tree
+        let mut empty = SegmentTree::<Sum, Add>::init(&[]);
+        assert_eq!(empty.query(..), Ok(0));
+        assert_eq!(empty.add_range(.., 1), Ok(()));
+        assert_eq!(empty.to_vec(), Vec::<i64>::new());
+        assert_eq!(empty.max_right(0, |_| true), Ok(0));
+        assert_eq!(empty.min_left(0, |_| true), Ok(0));
+        assert_eq!(
+            empty.set(0, 1),
+            Err(RangeError::IndexOutOfBounds { index: 0, len: 0 })
+        );
+    }
+
+    #[test]
+    fn test_searches_under_pending_tags() {
+        let values: Vec<i64> = vec![3, 1, 4, 1, 5, 9, 2, 6, 5, 3];
+        let mut tree = SegmentTree::<Sum, Add>::init(&values);
+        let mut expected = values.clone();
+
+        // leave pending tags on the inner nodes
+        tree.add_range(2..7, 2).unwrap();
+        tree.add_range(..4, -1).unwrap();
+        for (i, v) in expected.iter_mut().enumerate() {
+            *v += if (2..7).contains(&i) { 2 } else { 0 } - if i < 4 { 1 } else { 0 };
+        }
+
+        for bound in [0, 5, 12, 20, 100] {
+            for l in 0..=expected.len() {
+                let mut r = l;
+                while r < expected.len() && expected[l..=r].iter().sum::<i64>() <= bound {
+                    r += 1;
+                }
+                assert_eq!(tree.max_right(l, |s| *s <= bound), Ok(r));
+            }
+            for r in 0..=expected.len() {
+                let mut l = r;
+                while l > 0 && expected[l - 1..r].iter().sum::<i64>() <= bound {
+                    l -= 1;
+                }
+                assert_eq!(tree.min_left(r, |s| *s <= bound), Ok(l));
+            }
+        }
+
+        assert_eq!(
+            tree.max_right(11, |_| true),
+            Err(RangeError::OutOfBounds { end: 11, len: 10 })
+        );
+        assert_eq!(
+            tree.min_left(11, |_| true),
+            Err(RangeError::OutOfBounds { end: 11, len: 10 })
+        );
+
+        // first position holding at least 7, on a Max tree with pending chmin tags
+        let mut max_tree = SegmentTree::<Max, ChMin>::init(&[3, 8, 4, 9, 7, 2]);
+        max_tree.update_range(..4, 6).unwrap();
+        assert_eq!(
+            max_tree.find_first(.., |m| *m >= 7, |m| *m >= 7),
+            Ok(Some(4))
+        );
+        assert_eq!(max_tree.find_first(..4, |m| *m >= 7, |m| *m >= 7), Ok(None));
+        assert_eq!(
+            max_tree.find_first(1..1, |m| *m >= 0, |m| *m >= 0),
+            Ok(None)
+        );
+
+        // exact value: the equality alone cannot prune the inner nodes, it needs the monotone bound
+        let min_tree = SegmentTree::<Min>::init(&[1, 2, 3]);
+        assert_eq!(
+            min_tree.find_first(.., |m| *m <= 2, |v| *v == 2),
+            Ok(Some(1))
+        );
+        assert_eq!(min_tree.find_first(2.., |m| *m <= 2, |v| *v == 2), Ok(None));
+        assert_eq!(
+            max_tree.find_first(.., |m| *m >= 6, |v| *v == 6),
+            Ok(Some(1))
+        );
+        assert_eq!(
+            max_tree.find_first(2.., |m| *m >= 6, |v| *v == 6),
+            Ok(Some(3))
+        );
+    }
+
+    #[test]
+    fn test_exact_coverage_position() {
+        // coverage: [1, 2, 2, 1, 0, 0, 1]
+        let freq = build_frequency_array(7, &[(0, 2), (1, 3), (6, 6)]).unwrap();
+        let mut tree = SegmentTree::<Min<i32>, ChMin<i32>>::init(&freq);
+
+        assert_eq!(tree.exact_coverage_position(.., 2), Ok(Some(1)));
+        assert_eq!(tree.exact_coverage_position(3.., 1), Ok(Some(3)));
+        assert_eq!(tree.exact_coverage_position(4.., 1), Ok(Some(6)));
+        assert_eq!(tree.exact_coverage_position(4..6, 1), Ok(None));
+        assert_eq!(tree.exact_coverage_position(.., 3), Ok(None));
+
+        // positions 0, 3 and 6 are covered once, but none of them is in 4..=5
+        assert_eq!(tree.exists_exact_coverage(4..=5, 1), Ok(false));
+
+        // the search sees the updates still pending on the inner nodes
+        tree.update_range(..3, 1).unwrap();
+        assert_eq!(tree.exact_coverage_position(.., 2), Ok(None));
+        assert_eq!(tree.exact_coverage_position(.., 1), Ok(Some(0)));
+    }
+
+    #[test]
+    fn test_dynamic_coverage() {
+        let n = 30;
+        let mut tree = SegmentTree::coverage_from_segments(n, &[(0, 9), (5, 20)]).unwrap();
+        let mut coverage = vec![0; n];
+        let mut segments = vec![(0, 9), (5, 20)];
+        for &(l, r) in &segments {
+            coverage[l..=r].iter_mut().for_each(|c| *c += 1);
+        }
+
+        let mut rng = Lcg(2024);
+
+        for _ in 0..1000 {
+            match rng.next(3) {
+                0 => {
+                    let a = rng.next(n);
+                    let b = rng.next(n);
+                    let (l, r) = (a.min(b), a.max(b));
+                    tree.add_segment(l..=r).unwrap();
+                    coverage[l..=r].iter_mut().for_each(|c| *c += 1);
+                    segments.push((l, r));
+                }
+                1 if !segments.is_empty() => {
+                    let (l, r) = segments.swap_remove(rng.next(segments.len()));
+                    tree.remove_segment(l..=r).unwrap();
+                    coverage[l..=r].iter_mut().for_each(|c| *c -= 1);
+                }
+                _ => {
+                    let a = rng.next(n + 1);
+                    let b = rng.next(n + 1);
+                    let (l, r) = (a.min(b), a.max(b));
+                    let k = rng.next(5) as i32;
+                    let expected = (l..r).find(|&i| coverage[i] == k);
+                    assert_eq!(tree.exact_coverage_position(l..r, k), Ok(expected));
+                    assert_eq!(tree.exists_exact_coverage(l..r, k), Ok(expected.is_some()));
+                }
+            }
+        }
+
+        assert_eq!(
+            tree.to_vec(),
+            coverage.iter().map(|&c| (c, c)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            tree.add_segment(..=n),
+            Err(RangeError::OutOfBounds { end: n + 1, len: n })
+        );
+
+        // the initial segments are checked like the ones added later
+        let mut small = SegmentTree::coverage(3);
+        for (l, r) in [(1, 9), (5, 6), (2, 0)] {
+            let error = small.add_segment(l..=r).unwrap_err();
+            assert_eq!(
+                SegmentTree::coverage_from_segments(3, &[(0, 0), (l, r)]).err(),
+                Some(error)
+            );
+        }
+        assert_eq!(
+            SegmentTree::coverage_from_segments(3, &[(1, 9)]).err(),
+            Some(RangeError::OutOfBounds { end: 10, len: 3 })
+        );
+    }
+
+    #[test]
+    fn test_coverage_counts() {
+        let n = 25;
+        let mut segments = vec![(0, 4), (2, 10), (8, 8), (15, 24)];
+        let mut tree = SegmentTree::coverage_counts_from_segments(n, &segments).unwrap();
+        let mut coverage = build_frequency_array(n, &segments).unwrap();
+
+        let mut rng = Lcg(99);
+
+        for _ in 0..1000 {
+            match rng.next(3) {
+                0 => {
+                    let a = rng.next(n);
+                    let b = rng.next(n);
+                    let (l, r) = (a.min(b), a.max(b));
+                    tree.add_segment(l..=r).unwrap();
+                    coverage[l..=r].iter_mut().for_each(|c| *c += 1);
+                    segments.push((l, r));
+                }
+                1 if !segments.is_empty() => {
+                    let (l, r) = segments.swap_remove(rng.next(segments.len()));
+                    tree.remove_segment(l..=r).unwrap();
+                    coverage[l..=r].iter_mut().for_each(|c| *c -= 1);
+                }
+                _ => {
+                    let a = rng.next(n + 1);
+                    let b = rng.next(n + 1);
+                    let (l, r) = (a.min(b), a.max(b));
+                    let k = rng.next(5) as i32;
+                    let slice = &coverage[l..r];
+                    let exact = slice.iter().filter(|&&c| c == k).count();
+                    let at_least = slice.iter().filter(|&&c| c >= k).count();
+                    let zero = slice.iter().filter(|&&c| c == 0).count();
+                    assert_eq!(tree.count_exact(l..r, k), Ok(exact));
+                    assert_eq!(tree.count_at_least(l..r, k), Ok(at_least));
+                    assert_eq!(tree.count_zero(l..r), Ok(zero));
+                }
+            }
+        }
+
+        assert_eq!(
+            tree.count_exact(3..=n, 1),
+            Err(RangeError::OutOfBounds { end: n + 1, len: n })
+        );
+        assert_eq!(
+            SegmentTree::coverage_counts_from_segments(3, &[(0, 1), (1, 9)]).err(),
+            Some(RangeError::OutOfBounds { end: 10, len: 3 })
+        );
+        assert_eq!(
+            SegmentTree::coverage_counts_from_segments(3, &[(5, 6)]).err(),
+            Some(RangeError::OutOfBounds { end: 7, len: 3 })
+        );
+    }
+
+    #[test]
+    fn test_shared_reads_across_threads() {
+        use std::sync::Arc;
+        use std::thread;
+
+        let n = 1000;
+        let mut tree = SegmentTree::<Sum, AddAssign>::init(&vec![1; n]);
+        let mut expected = vec![1i64; n];
+
+        // leave plenty of tags pending on the inner nodes
+        for step in 0..50 {
+            let (l, r) = ((step * 37) % n, (step * 37) % n + n / 4);
+            let r = r.min(n);
+            if step % 3 == 0 {
+                tree.assign_range(l..r, step as i64).unwrap();
+                expected[l..r].iter_mut().for_each(|v| *v = step as i64);
+            } else {
+                tree.add_range(l..r, 2).unwrap();
+                expected[l..r].iter_mut().for_each(|v| *v += 2);
+            }
+        }
+
+        let tree = Arc::new(tree);
+        let expected = Arc::new(expected);
+        let readers: Vec<_> = (0..4)
+            .map(|t| {
+                let tree = Arc::clone(&tree);
+                let expected = Arc::clone(&expected);
+                thread::spawn(move || {
+                    for l in (t..n).step_by(7) {
+                        let r = (l + 3 * t + 10).min(n);
+                        assert_eq!(tree.query(l..r), Ok(expected[l..r].iter().sum()));
+                        assert_eq!(tree.get(l), Ok(expected[l]));
+                    }
+                    assert_eq!(tree.to_vec(), *expected);
+                })
+            })
+            .collect();
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        // the searches read through the pending tags too
+        let values = [4, 7, 1, 9, 3, 8];
+        let mut min_tree = SegmentTree::<Min<i32>, ChMin<i32>>::init(&values);
+        min_tree.update_range(3..5, 2).unwrap();
+        let min_tree = &min_tree;
+        assert_eq!(min_tree.exact_coverage_position(.., 2), Ok(Some(3)));
+        assert_eq!(
+            min_tree.find_first(4.., |m| *m <= 2, |m| *m <= 2),
+            Ok(Some(4))
+        );
+        assert_eq!(min_tree.to_vec(), vec![4, 7, 1, 2, 2, 8]);
+    }
+}
@@ -0,0 +1,211 @@
+use std::cmp::Reverse;
+use std::ops::RangeBounds;
+
+use crate::monoid::Monoid;
+use crate::range::RangeError;
+use crate::SegmentTree;
+
+/// Non-empty subarray `a[start..end]` with its sum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subarray {
+    pub sum: i64,
+    pub start: usize,
+    pub end: usize, // exclusive
+}
+
+impl Subarray {
+    // the better of two subarrays: larger sum, then leftmost start, then shortest
+    fn better(self, other: Subarray) -> Subarray {
+        let key = |s: &Subarray| (s.sum, Reverse(s.start), Reverse(s.end));
+        if key(&other) > key(&self) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Summary of a non-empty block `a[start..end]`: its sum, and its best prefix, suffix and subarray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Block {
+    pub sum: i64,
+    pub prefix: Subarray, // starts at the start of the block
+    pub suffix: Subarray, // ends at the end of the block
+    pub best: Subarray,
+}
+
+impl Block {
+    /// Block made of the single value `a[i] = value`.
+    pub fn leaf(i: usize, value: i64) -> Self {
+        let only = Subarray {
+            sum: value,
+            start: i,
+            end: i + 1,
+        };
+        Self {
+            sum: value,
+            prefix: only,
+            suffix: only,
+            best: only,
+        }
+    }
+}
+
+/// Custom node of the max-subarray tree: `None` is the empty range, and two blocks are combined
+/// under the assumption that the first one ends where the second one starts.
+pub struct MaxSubarray;
+
+impl Monoid for MaxSubarray {
+    type Value = Option<Block>;
+
+    fn identity() -> Option<Block> {
+        None
+    }
+
+    fn combine(a: &Option<Block>, b: &Option<Block>) -> Option<Block> {
+        let (a, b) = match (a, b) {
+            (Some(a), Some(b)) => (a, b),
+            (Some(block), None) | (None, Some(block)) => return Some(*block),
+            (None, None) => return None,
+        };
+
+        let whole_a_then = |s: Subarray| Subarray {
+            sum: a.sum + s.sum,
+            start: a.prefix.start,
+            ..s
+        };
+        let then_whole_b = |s: Subarray| Subarray {
+            sum: s.sum + b.sum,
+            end: b.suffix.end,
+            ..s
+        };
+        let crossing = Subarray {
+            sum: a.suffix.sum + b.prefix.sum,
+            start: a.suffix.start,
+            end: b.prefix.end,
+        };
+
+        Some(Block {
+            sum: a.sum + b.sum,
+            prefix: a.prefix.better(whole_a_then(b.prefix)),
+            suffix: then_whole_b(a.suffix).better(b.suffix),
+            best: a.best.better(crossing).better(b.best),
+        })
+    }
+}
+
+/// Maximum-sum contiguous subarray of any range, under point updates, in O(log n).
+///
+/// Among the subarrays with the largest sum, the one that starts first is returned,
+/// and the shortest of those if several start there.
+pub struct MaxSubarrayTree {
+    tree: SegmentTree<MaxSubarray>,
+}
+
+impl MaxSubarrayTree {
+    pub fn new(a: &[i64]) -> Self {
+        let leaves: Vec<Option<Block>> = a
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| Some(Block::leaf(i, value)))
+            .collect();
+        Self {
+            tree: SegmentTree::init(&leaves),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.n == 0
+    }
+
+    /// `a[i] = value`.
+    pub fn set(&mut self, i: usize, value: i64) -> Result<(), RangeError> {
+        self.tree.set(i, Some(Block::leaf(i, value)))
+    }
+
+    /// Current value of `a[i]`.
+    pub fn get(&self, i: usize) -> Result<i64, RangeError> {
+        Ok(self.tree.get(i)?.map_or(0, |block| block.sum))
+    }
+
+    /// Maximum-sum non-empty subarray of `a[range]`, or `None` for an empty range.
+    pub fn query(&self, range: impl RangeBounds<usize>) -> Result<Option<Subarray>, RangeError> {
+        Ok(self.tree.query(range)?.map(|block| block.best))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    fn brute_force(a: &[i64], l: usize, r: usize) -> Option<Subarray> {
+        let mut best: Option<Subarray> = None;
+        for start in l..r {
+            for end in start + 1..=r {
+                let candidate = Subarray {
+                    sum: a[start..end].iter().sum(),
+                    start,
+                    end,
+                };
+                best = Some(best.map_or(candidate, |best| best.better(candidate)));
+            }
+        }
+        best
+    }
+
+    #[test]
+    fn test_max_subarray() {
+        let tree = MaxSubarrayTree::new(&[-2, 1, -3, 4, -1, 2, 1, -5, 4]);
+        let expected = Subarray {
+            sum: 6,
+            start: 3,
+            end: 7,
+        };
+        assert_eq!(tree.query(..), Ok(Some(expected)));
+        assert_eq!(tree.query(4..4), Ok(None));
+        assert_eq!(
+            tree.query(..2),
+            Ok(Some(Subarray {
+                sum: 1,
+                start: 1,
+                end: 2
+            }))
+        );
+        // all negative: the largest single value
+        assert_eq!(tree.query(7..8).unwrap().unwrap().sum, -5);
+        assert_eq!(
+            tree.query(..10),
+            Err(RangeError::OutOfBounds { end: 10, len: 9 })
+        );
+
+        let empty = MaxSubarrayTree::new(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.query(..), Ok(None));
+    }
+
+    #[test]
+    fn test_max_subarray_under_updates() {
+        let mut rng = Lcg(31);
+        for n in [1, 2, 5, 16, 23] {
+            // small values make ties between subarrays frequent
+            let mut a: Vec<i64> = (0..n).map(|_| rng.next(7) as i64 - 3).collect();
+            let mut tree = MaxSubarrayTree::new(&a);
+
+            for _ in 0..200 {
+                let i = rng.next(n);
+                a[i] = rng.next(7) as i64 - 3;
+                tree.set(i, a[i]).unwrap();
+                assert_eq!(tree.get(i), Ok(a[i]));
+
+                let (x, y) = (rng.next(n + 1), rng.next(n + 1));
+                let (l, r) = (x.min(y), x.max(y));
+                assert_eq!(tree.query(l..r), Ok(brute_force(&a, l, r)));
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::ops::BitXor;
+
+use crate::num::{Bounded, Number};
+
+/// The operation used by a `SegmentTree` to summarize a range: `combine` must be associative,
+/// and `identity` must leave any value unchanged when combined with it (it is the summary of an empty range).
+///
+/// Custom node types (e.g. the nodes of a max-subarray tree) are supported by implementing this trait
+/// on a marker type with the node as `Value`.
+pub trait Monoid {
+    type Value: Clone;
+
+    fn identity() -> Self::Value;
+    fn combine(a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+/// Combination of `times` copies of `value` (the identity for 0), in O(log times) combinations.
+/// This is the summary of a range where every position holds `value`.
+pub fn power<M: Monoid>(value: &M::Value, times: usize) -> M::Value {
+    let mut result = M::identity();
+    let mut square = value.clone();
+    let mut remaining = times;
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = M::combine(&result, &square);
+        }
+        remaining >>= 1;
+        if remaining > 0 {
+            square = M::combine(&square, &square);
+        }
+    }
+    result
+}
+
+// The numeric monoids default to `i64`, like the actions of `lazy`, so that they pair without
+// type arguments (`SegmentTree::<Min, Add>`). Other value types are spelled out, e.g. `Min<i32>`.
+
+/// Minimum of the range.
+pub struct Min<T = i64>(PhantomData<T>);
+
+impl<T: Bounded> Monoid for Min<T> {
+    type Value = T;
+
+    fn identity() -> T {
+        T::max_value()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a.min(b)
+    }
+}
+
+/// Maximum of the range.
+pub struct Max<T = i64>(PhantomData<T>);
+
+impl<T: Bounded> Monoid for Max<T> {
+    type Value = T;
+
+    fn identity() -> T {
+        T::min_value()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a.max(b)
+    }
+}
+
+/// Minimum and maximum of the range, as a `(min, max)` pair.
+pub struct MinMax<T = i64>(PhantomData<T>);
+
+impl<T: Bounded> Monoid for MinMax<T> {
+    type Value = (T, T);
+
+    fn identity() -> (T, T) {
+        (T::max_value(), T::min_value())
+    }
+
+    fn combine(a: &(T, T), b: &(T, T)) -> (T, T) {
+        (a.0.min(b.0), a.1.max(b.1))
+    }
+}
+
+/// Minimum of the range together with how many positions hold it, as a `(min, count)` pair.
+pub struct MinCount<T = i64>(PhantomData<T>);
+
+impl<T: Bounded> Monoid for MinCount<T> {
+    type Value = (T, usize);
+
+    fn identity() -> (T, usize) {
+        (T::max_value(), 0)
+    }
+
+    fn combine(a: &(T, usize), b: &(T, usize)) -> (T, usize) {
+        match a.0.cmp(&b.0) {
+            Ordering::Less => *a,
+            Ordering::Greater => *b,
+            Ordering::Equal => (a.0, a.1 + b.1),
+        }
+    }
+}
+
+/// Monoids where combining a value with itself changes nothing (`combine(a, a) == a`):
+/// a range can then be summarized from two overlapping pieces, as in `SparseTable`.
+pub trait Idempotent: Monoid {}
+
+impl<T: Bounded> Idempotent for Min<T> {}
+impl<T: Bounded> Idempotent for Max<T> {}
+impl<T: Bounded> Idempotent for MinMax<T> {}
+
+/// Sum of the range.
+pub struct Sum<T = i64>(PhantomData<T>);
+
+impl<T: Number> Monoid for Sum<T> {
+    type Value = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a + *b
+    }
+}
+
+/// Greatest common divisor of the range (0 is the identity, gcd(0, x) = x).
+pub struct Gcd;
+
+impl Monoid for Gcd {
+    type Value = u64;
+
+    fn identity() -> u64 {
+        0
+    }
+
+    fn combine(a: &u64, b: &u64) -> u64 {
+        gcd(*a, *b)
+    }
+}
+
+impl Idempotent for Gcd {}
+
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Bitwise xor of the range.
+pub struct Xor<T = u64>(PhantomData<T>);
+
+impl<T: Number + BitXor<Output = T>> Monoid for Xor<T> {
+    type Value = T;
+
+    fn identity() -> T {
+        T::zero()
+    }
+
+    fn combine(a: &T, b: &T) -> T {
+        *a ^ *b
+    }
+}
+
+/// Product of the range modulo `P`. Values are expected to be already reduced modulo `P`.
+pub struct ProductMod<const P: u64>;
+
+impl<const P: u64> Monoid for ProductMod<P> {
+    type Value = u64;
+
+    fn identity() -> u64 {
+        1 % P
+    }
+
+    fn combine(a: &u64, b: &u64) -> u64 {
+        (*a as u128 * *b as u128 % P as u128) as u64
+    }
+}
+
+/// Monoids where the order of the operands does not matter (`combine(a, b) == combine(b, a)`):
+/// a range can then be summarized from its pieces in any order, as in `SegmentTree2D`.
+pub trait Commutative: Monoid {}
+
+impl<T: Bounded> Commutative for Min<T> {}
+impl<T: Bounded> Commutative for Max<T> {}
+impl<T: Bounded> Commutative for MinMax<T> {}
+impl<T: Bounded> Commutative for MinCount<T> {}
+impl<T: Number> Commutative for Sum<T> {}
+impl Commutative for Gcd {}
+impl<T: Number + BitXor<Output = T>> Commutative for Xor<T> {}
+impl<const P: u64> Commutative for ProductMod<P> {}
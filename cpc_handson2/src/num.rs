@@ -0,0 +1,211 @@
+use std::fmt::{self, Debug};
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// Numeric values that can be summed and multiplied, used by the arithmetic monoids.
+pub trait Number:
+    Copy + PartialEq + Debug + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+
+    // converts the length of a segment, needed to apply updates to sums
+    fn from_usize(n: usize) -> Self;
+}
+
+/// Totally ordered values with a smallest and a largest element,
+/// used as identities by the `Min` and `Max` monoids.
+pub trait Bounded: Copy + Ord + Debug {
+    fn min_value() -> Self;
+    fn max_value() -> Self;
+}
+
+macro_rules! impl_primitive {
+    ($($t:ty),*) => {
+        $(
+            impl Number for $t {
+                fn zero() -> Self {
+                    0
+                }
+
+                fn one() -> Self {
+                    1
+                }
+
+                fn from_usize(n: usize) -> Self {
+                    n as $t
+                }
+            }
+
+            impl Bounded for $t {
+                fn min_value() -> Self {
+                    <$t>::MIN
+                }
+
+                fn max_value() -> Self {
+                    <$t>::MAX
+                }
+            }
+        )*
+    };
+}
+
+impl_primitive!(i32, i64, i128, u32, u64, usize);
+
+/// Integer modulo `P`, always kept reduced in `0..P`. `P` must be below 2^63 so that sums do not
+/// overflow, and prime for `inv`.
+///
+/// Implements `Number`, so it works with the arithmetic monoids and actions,
+/// e.g. `SegmentTree<Sum<ModInt<P>>, Affine<ModInt<P>>>` for range affine updates and sums mod `P`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub struct ModInt<const P: u64>(u64);
+
+impl<const P: u64> ModInt<P> {
+    pub fn new(value: u64) -> Self {
+        Self(value % P)
+    }
+
+    /// The representative in `0..P`.
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+
+    /// `self^exp`, by binary exponentiation.
+    pub fn pow(&self, mut exp: u64) -> Self {
+        let (mut result, mut base) = (Self::new(1), *self);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exp >>= 1;
+        }
+        result
+    }
+
+    /// Multiplicative inverse (`self^(P - 2)`, by Fermat's little theorem), or `None` for 0.
+    pub fn inv(&self) -> Option<Self> {
+        (self.0 != 0).then(|| self.pow(P - 2))
+    }
+}
+
+impl<const P: u64> From<i64> for ModInt<P> {
+    fn from(value: i64) -> Self {
+        Self(value.rem_euclid(P as i64) as u64)
+    }
+}
+
+impl<const P: u64> fmt::Display for ModInt<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<const P: u64> Add for ModInt<P> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        let sum = self.0 + other.0;
+        Self(if sum >= P { sum - P } else { sum })
+    }
+}
+
+impl<const P: u64> Sub for ModInt<P> {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self(if self.0 >= other.0 {
+            self.0 - other.0
+        } else {
+            self.0 + P - other.0
+        })
+    }
+}
+
+impl<const P: u64> Mul for ModInt<P> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Self((self.0 as u128 * other.0 as u128 % P as u128) as u64)
+    }
+}
+
+impl<const P: u64> Neg for ModInt<P> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(0) - self
+    }
+}
+
+impl<const P: u64> Number for ModInt<P> {
+    fn zero() -> Self {
+        Self::new(0)
+    }
+
+    fn one() -> Self {
+        Self::new(1)
+    }
+
+    fn from_usize(n: usize) -> Self {
+        Self::new(n as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iterative::IterativeSegmentTree;
+    use crate::lazy::{Affine, AffineTag};
+    use crate::monoid::Sum;
+    use crate::test_util::Lcg;
+    use crate::SegmentTree;
+
+    const P: u64 = 998_244_353;
+    type Mint = ModInt<P>;
+
+    #[test]
+    fn test_mod_int_arithmetic() {
+        let (a, b) = (Mint::new(P - 1), Mint::new(5));
+        assert_eq!((a + b).value(), 4);
+        assert_eq!((b - a).value(), 6);
+        assert_eq!((a * a).value(), 1);
+        assert_eq!((-b).value(), P - 5);
+        assert_eq!(Mint::from(-3), Mint::new(P - 3));
+        assert_eq!(Mint::new(P + 2), Mint::new(2));
+
+        assert_eq!(Mint::new(2).pow(10).value(), 1024);
+        assert_eq!(b * b.inv().unwrap(), Mint::one());
+        assert_eq!(Mint::zero().inv(), None);
+        assert_eq!(Mint::new(42).to_string(), "42");
+    }
+
+    #[test]
+    fn test_range_affine_range_sum_mod() {
+        let n = 30;
+        let mut rng = Lcg(37);
+        let mut a: Vec<u64> = (0..n).map(|_| rng.next(P as usize) as u64).collect();
+        let values: Vec<Mint> = a.iter().map(|&x| Mint::new(x)).collect();
+        let mut tree = SegmentTree::<Sum<Mint>, Affine<Mint>>::init(&values);
+        let mut iterative = IterativeSegmentTree::<Sum<Mint>, Affine<Mint>>::init(&values);
+
+        for _ in 0..300 {
+            let (x, y) = (rng.next(n + 1), rng.next(n + 1));
+            let (l, r) = (x.min(y), x.max(y));
+
+            if rng.next(2) == 0 {
+                // a[i] = b * a[i] + c over [l, r), with b and c anywhere in 0..P
+                let (b, c) = (rng.next(P as usize) as u64, rng.next(P as usize) as u64);
+                let tag = AffineTag::new(Mint::new(b), Mint::new(c));
+                tree.apply_range(l..r, tag).unwrap();
+                iterative.apply_range(l..r, tag).unwrap();
+                for v in &mut a[l..r] {
+                    *v = ((b as u128 * *v as u128 + c as u128) % P as u128) as u64;
+                }
+            } else {
+                let expected = a[l..r].iter().fold(0, |sum, &v| (sum + v) % P);
+                assert_eq!(tree.query(l..r).unwrap().value(), expected);
+                assert_eq!(iterative.query(l..r).unwrap().value(), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::ops::RangeBounds;
+
+use crate::monoid::{Monoid, Sum};
+use crate::range::{check_index, to_closed, RangeError};
+
+/// Handle on one state of a `PersistentSegmentTree`. Only valid for the tree that returned it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version(usize);
+
+struct Node<V> {
+    value: V,
+    left: usize,
+    right: usize,
+}
+
+/// Segment tree that keeps all its past states. A point update copies the O(log n) nodes on the path
+/// to the updated leaf and shares the rest with the previous version, so every version stays
+/// queryable at the cost of O(log n) extra memory per update.
+pub struct PersistentSegmentTree<M: Monoid> {
+    n: usize,
+    nodes: Vec<Node<M::Value>>,
+    roots: Vec<usize>, // root node of each version, in creation order
+}
+
+impl<M: Monoid> PersistentSegmentTree<M> {
+    // --------------------- CONSTRUCTOR ---------------------
+
+    /// Builds the tree over `a`, as the initial version (see `initial`).
+    pub fn init(a: &[M::Value]) -> Self {
+        let mut tree = Self {
+            n: a.len(),
+            nodes: Vec::with_capacity(2 * a.len()),
+            roots: Vec::new(),
+        };
+
+        let root = if a.is_empty() {
+            tree.new_node(M::identity(), 0, 0)
+        } else {
+            tree.build(a, 0, a.len() - 1)
+        };
+        tree.roots.push(root);
+        tree
+    }
+
+    fn build(&mut self, a: &[M::Value], start: usize, end: usize) -> usize {
+        if start == end {
+            return self.new_node(a[start].clone(), 0, 0);
+        }
+
+        let mid = (start + end) / 2;
+        let left = self.build(a, start, mid);
+        let right = self.build(a, mid + 1, end);
+        self.new_parent(left, right)
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    // --------------------- VERSIONS ---------------------
+
+    /// The version built by `init`.
+    pub fn initial(&self) -> Version {
+        Version(0)
+    }
+
+    /// The most recently created version.
+    pub fn latest(&self) -> Version {
+        Version(self.roots.len() - 1)
+    }
+
+    /// The version created by the `t`-th update (the initial one for `t = 0`), if any.
+    pub fn version(&self, t: usize) -> Option<Version> {
+        (t < self.roots.len()).then_some(Version(t))
+    }
+
+    /// Number of versions, the initial one included.
+    pub fn version_count(&self) -> usize {
+        self.roots.len()
+    }
+
+    // --------------------- UPDATE ---------------------
+
+    /// Creates a new version equal to `version`, except for `a[i] = value`, and returns its handle.
+    /// `version` itself is left unchanged.
+    pub fn set(
+        &mut self,
+        version: Version,
+        i: usize,
+        value: M::Value,
+    ) -> Result<Version, RangeError> {
+        check_index(i, self.n)?;
+
+        let root = self.set_rec(self.roots[version.0], 0, self.n - 1, i, value);
+        self.roots.push(root);
+        Ok(self.latest())
+    }
+
+    // copy the path from `node` to the leaf i, and return the copy of `node`
+    fn set_rec(
+        &mut self,
+        node: usize,
+        start: usize,
+        end: usize,
+        i: usize,
+        value: M::Value,
+    ) -> usize {
+        if start == end {
+            return self.new_node(value, 0, 0);
+        }
+
+        let mid = (start + end) / 2;
+        let (mut left, mut right) = (self.nodes[node].left, self.nodes[node].right);
+        if i <= mid {
+            left = self.set_rec(left, start, mid, i, value);
+        } else {
+            right = self.set_rec(right, mid + 1, end, i, value);
+        }
+        self.new_parent(left, right)
+    }
+
+    // --------------------- QUERY ---------------------
+
+    /// Combination of the values in `range`, as they were in `version` (the identity for an empty range).
+    pub fn query(
+        &self,
+        version: Version,
+        range: impl RangeBounds<usize>,
+    ) -> Result<M::Value, RangeError> {
+        Ok(match to_closed(range, self.n)? {
+            Some((l, r)) => self.query_rec(self.roots[version.0], 0, self.n - 1, l, r),
+            None => M::identity(),
+        })
+    }
+
+    fn query_rec(&self, node: usize, start: usize, end: usize, l: usize, r: usize) -> M::Value {
+        if start > r || end < l {
+            // no overlap
+            return M::identity();
+        }
+
+        if start >= l && end <= r {
+            // total overlap
+            return self.nodes[node].value.clone();
+        }
+
+        let mid = (start + end) / 2;
+        M::combine(
+            &self.query_rec(self.nodes[node].left, start, mid, l, r),
+            &self.query_rec(self.nodes[node].right, mid + 1, end, l, r),
+        )
+    }
+
+    /// Value of `a[i]` in `version`.
+    pub fn get(&self, version: Version, i: usize) -> Result<M::Value, RangeError> {
+        check_index(i, self.n)?;
+        self.query(version, i..=i)
+    }
+
+    // --------------------- HELPERS ---------------------
+
+    fn new_node(&mut self, value: M::Value, left: usize, right: usize) -> usize {
+        self.nodes.push(Node { value, left, right });
+        self.nodes.len() - 1
+    }
+
+    fn new_parent(&mut self, left: usize, right: usize) -> usize {
+        let value = M::combine(&self.nodes[left].value, &self.nodes[right].value);
+        self.new_node(value, left, right)
+    }
+}
+
+/// K-th smallest value of any subarray, in O(log n) per query.
+///
+/// The values are replaced by their rank among the distinct values, and version `i` of a persistent
+/// tree counts how many of the first `i` values have each rank. The counts of `a[l..r]` are then the
+/// differences between versions `r` and `l`, and the k-th smallest is found by walking both versions
+/// down together.
+pub struct KthSmallest<T> {
+    sorted: Vec<T>, // distinct values, the rank of a value is its position here
+    counts: PersistentSegmentTree<Sum<usize>>,
+    prefix: Vec<Version>, // prefix[i] counts the ranks of a[..i]
+}
+
+impl<T: Ord + Clone> KthSmallest<T> {
+    pub fn new(a: &[T]) -> Self {
+        let mut sorted = a.to_vec();
+        sorted.sort();
+        sorted.dedup();
+
+        let mut counts = PersistentSegmentTree::<Sum<usize>>::init(&vec![0; sorted.len()]);
+        let mut prefix = vec![counts.initial()];
+        for value in a {
+            let rank = sorted.binary_search(value).unwrap();
+            let last = *prefix.last().unwrap();
+            let count = counts.get(last, rank).unwrap();
+            prefix.push(counts.set(last, rank, count + 1).unwrap());
+        }
+
+        Self {
+            sorted,
+            counts,
+            prefix,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.prefix.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The `k`-th smallest value of `a[range]`, counting from 1 and with repetitions,
+    /// or `None` if `k` is 0 or larger than the length of the range.
+    pub fn kth_smallest(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: usize,
+    ) -> Result<Option<T>, RangeError> {
+        let Some((l, r)) = to_closed(range, self.len())? else {
+            return Ok(None);
+        };
+        if k == 0 || k > r - l + 1 {
+            return Ok(None);
+        }
+
+        let nodes = &self.counts.nodes;
+        let mut before = self.counts.roots[self.prefix[l].0];
+        let mut after = self.counts.roots[self.prefix[r + 1].0];
+        let (mut start, mut end) = (0, self.sorted.len() - 1);
+        let mut remaining = k;
+
+        while start != end {
+            let mid = (start + end) / 2;
+            // values of the range whose rank is in the left half
+            let left_count = nodes[nodes[after].left].value - nodes[nodes[before].left].value;
+
+            if remaining <= left_count {
+                before = nodes[before].left;
+                after = nodes[after].left;
+                end = mid;
+            } else {
+                remaining -= left_count;
+                before = nodes[before].right;
+                after = nodes[after].right;
+                start = mid + 1;
+            }
+        }
+
+        Ok(Some(self.sorted[start].clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::Max;
+    use crate::test_util::Lcg;
+
+    #[test]
+    fn test_versioned_queries() {
+        let n = 20;
+        let mut tree = PersistentSegmentTree::<Max>::init(&vec![0; n]);
+        let mut snapshots = vec![vec![0; n]];
+
+        // every update derives from a random earlier version
+        let mut rng = Lcg(17);
+        for _ in 0..200 {
+            let parent = rng.next(tree.version_count());
+            let (i, value) = (rng.next(n), rng.next(100) as i64);
+
+            let version = tree.set(tree.version(parent).unwrap(), i, value).unwrap();
+            assert_eq!(version, tree.latest());
+
+            let mut snapshot = snapshots[parent].clone();
+            snapshot[i] = value;
+            snapshots.push(snapshot);
+        }
+
+        for (t, snapshot) in snapshots.iter().enumerate() {
+            let version = tree.version(t).unwrap();
+            for _ in 0..10 {
+                let (x, y) = (rng.next(n + 1), rng.next(n + 1));
+                let range = x.min(y)..x.max(y);
+                let expected = snapshot[range.clone()].iter().copied().max();
+                assert_eq!(tree.query(version, range), Ok(expected.unwrap_or(i64::MIN)));
+            }
+            assert_eq!(tree.get(version, t % n), Ok(snapshot[t % n]));
+        }
+
+        assert_eq!(tree.version(snapshots.len()), None);
+        assert_eq!(tree.get(tree.initial(), 3), Ok(0));
+        assert_eq!(
+            tree.set(tree.initial(), n, 1),
+            Err(RangeError::IndexOutOfBounds { index: n, len: n })
+        );
+    }
+
+    #[test]
+    fn test_kth_smallest() {
+        let a = [5, 1, 4, 1, 9, 2, 6, 5, 3, 5];
+        let kth = KthSmallest::new(&a);
+
+        for l in 0..=a.len() {
+            for r in l..=a.len() {
+                let mut sorted = a[l..r].to_vec();
+                sorted.sort();
+                for k in 0..=sorted.len() + 1 {
+                    let expected = k.checked_sub(1).and_then(|i| sorted.get(i)).copied();
+                    assert_eq!(kth.kth_smallest(l..r, k), Ok(expected));
+                }
+            }
+        }
+
+        assert_eq!(
+            kth.kth_smallest(..=a.len(), 1),
+            Err(RangeError::OutOfBounds {
+                end: a.len() + 1,
+                len: a.len()
+            })
+        );
+
+        let empty = KthSmallest::<i32>::new(&[]);
+        assert_eq!(empty.kth_smallest(.., 1), Ok(None));
+    }
+}
@@ -0,0 +1,131 @@
+use std::error::Error;
+use std::fmt;
+use std::ops::{Bound, RangeBounds};
+
+// Index convention shared by all the trees of the crate: positions are 0-based, and ranges are given
+// with the standard range syntax (`2..=7`, `3..`, `..`), so that `..n` covers an array of length `n`.
+
+/// Error returned when a range or an index does not fit in the array behind a tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeError {
+    /// The range starts after its end, e.g. `5..2`.
+    Decreasing { start: usize, end: usize },
+    /// The range goes past the end of the array (`end` is exclusive).
+    OutOfBounds { end: usize, len: usize },
+    /// The index is not a position of the array.
+    IndexOutOfBounds { index: usize, len: usize },
+}
+
+impl fmt::Display for RangeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RangeError::Decreasing { start, end } => {
+                write!(f, "range starts at {} but ends at {}", start, end)
+            }
+            RangeError::OutOfBounds { end, len } => {
+                write!(f, "range end {} is out of bounds for length {}", end, len)
+            }
+            RangeError::IndexOutOfBounds { index, len } => {
+                write!(f, "index {} is out of bounds for length {}", index, len)
+            }
+        }
+    }
+}
+
+impl Error for RangeError {}
+
+/// Converts `range` into the half-open interval `[start, end)` of an array of length `len`.
+pub fn to_half_open(
+    range: impl RangeBounds<usize>,
+    len: usize,
+) -> Result<(usize, usize), RangeError> {
+    let overflow = RangeError::OutOfBounds {
+        end: usize::MAX,
+        len,
+    };
+
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1).ok_or(overflow.clone())?,
+        Bound::Unbounded => 0,
+    };
+
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1).ok_or(overflow)?,
+        Bound::Excluded(&end) => end,
+        Bound::Unbounded => len,
+    };
+
+    if start > end {
+        return Err(RangeError::Decreasing { start, end });
+    }
+    if end > len {
+        return Err(RangeError::OutOfBounds { end, len });
+    }
+
+    Ok((start, end))
+}
+
+/// Converts `range` into the closed interval `[l, r]` used by the recursive trees,
+/// or `None` if the range is empty.
+pub fn to_closed(
+    range: impl RangeBounds<usize>,
+    len: usize,
+) -> Result<Option<(usize, usize)>, RangeError> {
+    let (start, end) = to_half_open(range, len)?;
+    Ok(if start == end {
+        None
+    } else {
+        Some((start, end - 1))
+    })
+}
+
+/// Checks that `index` is a position of an array of length `len`.
+pub fn check_index(index: usize, len: usize) -> Result<(), RangeError> {
+    if index < len {
+        Ok(())
+    } else {
+        Err(RangeError::IndexOutOfBounds { index, len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_range_conversion() {
+        assert_eq!(to_half_open(2..=7, 10), Ok((2, 8)));
+        assert_eq!(to_half_open(.., 10), Ok((0, 10)));
+        assert_eq!(to_half_open(3.., 10), Ok((3, 10)));
+        assert_eq!(to_half_open(..4, 10), Ok((0, 4)));
+        assert_eq!(to_closed(4..4, 10), Ok(None));
+        assert_eq!(to_closed(0..=0, 10), Ok(Some((0, 0))));
+
+        assert_eq!(
+            to_half_open(0..=10, 10),
+            Err(RangeError::OutOfBounds { end: 11, len: 10 })
+        );
+        assert_eq!(
+            to_half_open(..=usize::MAX, 10),
+            Err(RangeError::OutOfBounds {
+                end: usize::MAX,
+                len: 10
+            })
+        );
+        assert_eq!(
+            to_half_open((Bound::Included(5), Bound::Excluded(2)), 10),
+            Err(RangeError::Decreasing { start: 5, end: 2 })
+        );
+
+        assert_eq!(check_index(9, 10), Ok(()));
+        assert_eq!(
+            check_index(10, 10),
+            Err(RangeError::IndexOutOfBounds { index: 10, len: 10 })
+        );
+        assert_eq!(
+            RangeError::OutOfBounds { end: 11, len: 10 }.to_string(),
+            "range end 11 is out of bounds for length 10"
+        );
+    }
+}
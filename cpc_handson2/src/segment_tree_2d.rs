@@ -0,0 +1,245 @@
+use std::ops::RangeBounds;
+
+use crate::monoid::Commutative;
+use crate::range::{check_index, to_half_open, RangeError};
+
+/// Segment tree of segment trees over a `rows x cols` grid: point updates and rectangle queries
+/// in O(log rows * log cols), with the monoids of `SegmentTree`.
+///
+/// The outer tree is over the rows, and each of its nodes holds a tree over the columns whose leaves
+/// combine the rows of the node. A rectangle is therefore combined column by column inside a node
+/// and row block by row block across nodes, which gives the right answer only because `M` is
+/// `Commutative` (sum, min, max, gcd, xor, ... but not e.g. a max-subarray node).
+///
+/// Both dimensions use the bottom-up layout of `IterativeSegmentTree`, in one flat vector.
+pub struct SegmentTree2D<M: Commutative> {
+    rows: usize,
+    cols: usize,
+    row_size: usize, // rows and columns padded to powers of two
+    col_size: usize,
+    tree: Vec<M::Value>, // (2 * row_size) x (2 * col_size), row-major
+}
+
+impl<M: Commutative> SegmentTree2D<M> {
+    /// Grid of `rows x cols` identities.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let (row_size, col_size) = (rows.next_power_of_two(), cols.next_power_of_two());
+        Self {
+            rows,
+            cols,
+            row_size,
+            col_size,
+            tree: vec![M::identity(); 4 * row_size * col_size],
+        }
+    }
+
+    /// Builds the tree over `grid`, given row by row.
+    ///
+    /// # Panics
+    /// Panics if the rows do not all have the same length.
+    pub fn init(grid: &[Vec<M::Value>]) -> Self {
+        let cols = grid.first().map_or(0, Vec::len);
+        assert!(
+            grid.iter().all(|row| row.len() == cols),
+            "All the rows must have the same length"
+        );
+
+        let mut tree = Self::new(grid.len(), cols);
+        for (i, row) in grid.iter().enumerate() {
+            let r = tree.row_size + i;
+            for (j, value) in row.iter().enumerate() {
+                let cell = tree.cell(r, tree.col_size + j);
+                tree.tree[cell] = value.clone();
+            }
+            for c in (1..tree.col_size).rev() {
+                tree.pull_col(r, c);
+            }
+        }
+        for r in (1..tree.row_size).rev() {
+            for c in 1..2 * tree.col_size {
+                tree.pull_row(r, c);
+            }
+        }
+        tree
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// `a[row][col] = value`.
+    pub fn set(&mut self, row: usize, col: usize, value: M::Value) -> Result<(), RangeError> {
+        check_index(row, self.rows)?;
+        check_index(col, self.cols)?;
+
+        // update the leaf row, then every outer node above it on the same column path
+        let mut r = self.row_size + row;
+        let leaf = self.cell(r, self.col_size + col);
+        self.tree[leaf] = value;
+        let mut c = (self.col_size + col) >> 1;
+        while c > 0 {
+            self.pull_col(r, c);
+            c >>= 1;
+        }
+
+        r >>= 1;
+        while r > 0 {
+            let mut c = self.col_size + col;
+            while c > 0 {
+                self.pull_row(r, c);
+                c >>= 1;
+            }
+            r >>= 1;
+        }
+        Ok(())
+    }
+
+    /// Current value of `a[row][col]`.
+    pub fn get(&self, row: usize, col: usize) -> Result<M::Value, RangeError> {
+        check_index(row, self.rows)?;
+        check_index(col, self.cols)?;
+        Ok(self.tree[self.cell(self.row_size + row, self.col_size + col)].clone())
+    }
+
+    /// Combination of the rectangle made of the rows in `rows` and the columns in `cols`
+    /// (the identity for an empty rectangle).
+    pub fn query(
+        &self,
+        rows: impl RangeBounds<usize>,
+        cols: impl RangeBounds<usize>,
+    ) -> Result<M::Value, RangeError> {
+        let (top, bottom) = to_half_open(rows, self.rows)?;
+        let (left, right) = to_half_open(cols, self.cols)?;
+
+        let mut result = M::identity();
+        let (mut r_low, mut r_high) = (top + self.row_size, bottom + self.row_size);
+        while r_low < r_high {
+            if r_low & 1 == 1 {
+                result = M::combine(&result, &self.query_cols(r_low, left, right));
+                r_low += 1;
+            }
+            if r_high & 1 == 1 {
+                r_high -= 1;
+                result = M::combine(&result, &self.query_cols(r_high, left, right));
+            }
+            r_low >>= 1;
+            r_high >>= 1;
+        }
+        Ok(result)
+    }
+
+    // combination of the columns [left, right) in the tree of the outer node r
+    fn query_cols(&self, r: usize, left: usize, right: usize) -> M::Value {
+        let mut result = M::identity();
+        let (mut low, mut high) = (left + self.col_size, right + self.col_size);
+        while low < high {
+            if low & 1 == 1 {
+                result = M::combine(&result, &self.tree[self.cell(r, low)]);
+                low += 1;
+            }
+            if high & 1 == 1 {
+                high -= 1;
+                result = M::combine(&result, &self.tree[self.cell(r, high)]);
+            }
+            low >>= 1;
+            high >>= 1;
+        }
+        result
+    }
+
+    fn cell(&self, r: usize, c: usize) -> usize {
+        r * 2 * self.col_size + c
+    }
+
+    // recompute a column node from its two children, inside the tree of the outer node r
+    fn pull_col(&mut self, r: usize, c: usize) {
+        let cell = self.cell(r, c);
+        self.tree[cell] = M::combine(
+            &self.tree[self.cell(r, 2 * c)],
+            &self.tree[self.cell(r, 2 * c + 1)],
+        );
+    }
+
+    // recompute the column node c of the outer node r from the two outer children
+    fn pull_row(&mut self, r: usize, c: usize) {
+        let cell = self.cell(r, c);
+        self.tree[cell] = M::combine(
+            &self.tree[self.cell(2 * r, c)],
+            &self.tree[self.cell(2 * r + 1, c)],
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fenwick::Fenwick2D;
+    use crate::monoid::{Max, Sum};
+    use crate::test_util::Lcg;
+
+    #[test]
+    fn test_rectangle_queries() {
+        for (rows, cols) in [(1, 1), (3, 5), (8, 8), (6, 13)] {
+            let mut rng = Lcg((rows * 100 + cols) as u64);
+            let mut grid: Vec<Vec<i64>> = (0..rows)
+                .map(|_| (0..cols).map(|_| rng.next(50) as i64 - 25).collect())
+                .collect();
+
+            let mut sum_tree = SegmentTree2D::<Sum>::init(&grid);
+            let mut max_tree = SegmentTree2D::<Max>::init(&grid);
+            let mut fenwick = Fenwick2D::new(rows, cols);
+            for (i, row) in grid.iter().enumerate() {
+                for (j, &value) in row.iter().enumerate() {
+                    fenwick.add(i, j, value).unwrap();
+                }
+            }
+
+            for _ in 0..300 {
+                let (i, j) = (rng.next(rows), rng.next(cols));
+                let value = rng.next(50) as i64 - 25;
+                sum_tree.set(i, j, value).unwrap();
+                max_tree.set(i, j, value).unwrap();
+                fenwick.add(i, j, value - grid[i][j]).unwrap();
+                grid[i][j] = value;
+
+                let (a, b) = (rng.next(rows + 1), rng.next(rows + 1));
+                let (c, d) = (rng.next(cols + 1), rng.next(cols + 1));
+                let (row_range, col_range) = (a.min(b)..a.max(b), c.min(d)..c.max(d));
+                let cells = grid[row_range.clone()]
+                    .iter()
+                    .flat_map(|line| line[col_range.clone()].iter().copied());
+                let expected_max = cells.clone().max().unwrap_or(i64::MIN);
+                let expected_sum: i64 = cells.sum();
+
+                assert_eq!(
+                    sum_tree.query(row_range.clone(), col_range.clone()),
+                    Ok(expected_sum)
+                );
+                assert_eq!(
+                    fenwick.sum(row_range.clone(), col_range.clone()),
+                    Ok(expected_sum)
+                );
+                assert_eq!(max_tree.query(row_range, col_range), Ok(expected_max));
+                assert_eq!(max_tree.get(i, j), Ok(value));
+            }
+        }
+    }
+
+    #[test]
+    fn test_2d_errors() {
+        let mut tree = SegmentTree2D::<Sum>::new(2, 3);
+        assert_eq!(tree.query(.., ..), Ok(0));
+        assert_eq!(
+            tree.set(2, 0, 1),
+            Err(RangeError::IndexOutOfBounds { index: 2, len: 2 })
+        );
+        assert_eq!(
+            tree.query(.., 1..4),
+            Err(RangeError::OutOfBounds { end: 4, len: 3 })
+        );
+    }
+}
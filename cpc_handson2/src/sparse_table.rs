@@ -0,0 +1,171 @@
+use std::ops::RangeBounds;
+
+use crate::monoid::{Idempotent, Monoid};
+use crate::range::{to_closed, RangeError};
+
+/// Sparse table over a static array: O(n log n) build, then O(1) queries for idempotent monoids
+/// (min, max, gcd). `table[k][i]` is the combination of the `2^k` values starting at `i`, and a range
+/// is covered by the two (possibly overlapping) blocks of the largest power of two that fits in it.
+pub struct SparseTable<M: Idempotent> {
+    table: Vec<Vec<M::Value>>,
+}
+
+impl<M: Idempotent> SparseTable<M> {
+    pub fn init(a: &[M::Value]) -> Self {
+        let mut table = vec![a.to_vec()];
+
+        let mut width = 1;
+        while 2 * width <= a.len() {
+            let previous = table.last().unwrap();
+            let level = (0..=a.len() - 2 * width)
+                .map(|i| M::combine(&previous[i], &previous[i + width]))
+                .collect();
+            table.push(level);
+            width *= 2;
+        }
+
+        Self { table }
+    }
+
+    pub fn len(&self) -> usize {
+        self.table[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Combination of the values in `range` (the identity for an empty range).
+    pub fn query(&self, range: impl RangeBounds<usize>) -> Result<M::Value, RangeError> {
+        Ok(match to_closed(range, self.len())? {
+            Some((l, r)) => {
+                let level = (r - l + 1).ilog2() as usize;
+                let width = 1 << level;
+                M::combine(&self.table[level][l], &self.table[level][r + 1 - width])
+            }
+            None => M::identity(),
+        })
+    }
+}
+
+/// Disjoint sparse table: O(1) queries for any monoid, including non-idempotent ones such as sums.
+///
+/// The array is padded to a power of two. At level `h`, the array is cut into blocks of `2^(h+1)`
+/// positions, and each position stores the combination from itself to the middle of its block.
+/// A range `[l, r]` crosses the middle of exactly one block, the one of the highest bit where `l`
+/// and `r` differ, so its value is the combination of the two stored halves.
+pub struct DisjointSparseTable<M: Monoid> {
+    n: usize,
+    table: Vec<Vec<M::Value>>,
+}
+
+impl<M: Monoid> DisjointSparseTable<M> {
+    pub fn init(a: &[M::Value]) -> Self {
+        let size = a.len().next_power_of_two().max(2);
+        let mut values = vec![M::identity(); size];
+        values[..a.len()].clone_from_slice(a);
+
+        // level 0 has blocks of two positions, each half being a single value
+        let mut table = vec![values.clone()];
+        for h in 1..size.trailing_zeros() {
+            let half = 1 << h;
+            let mut level = vec![M::identity(); size];
+
+            for mid in (half..size).step_by(2 * half) {
+                // suffixes of the left half, ending at mid - 1
+                level[mid - 1] = values[mid - 1].clone();
+                for i in (mid - half..mid - 1).rev() {
+                    level[i] = M::combine(&values[i], &level[i + 1]);
+                }
+
+                // prefixes of the right half, starting at mid
+                level[mid] = values[mid].clone();
+                for i in mid + 1..mid + half {
+                    level[i] = M::combine(&level[i - 1], &values[i]);
+                }
+            }
+
+            table.push(level);
+        }
+
+        Self { n: a.len(), table }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// Combination of the values in `range` (the identity for an empty range).
+    pub fn query(&self, range: impl RangeBounds<usize>) -> Result<M::Value, RangeError> {
+        Ok(match to_closed(range, self.n)? {
+            Some((l, r)) if l == r => self.table[0][l].clone(),
+            Some((l, r)) => {
+                let level = (l ^ r).ilog2() as usize;
+                M::combine(&self.table[level][l], &self.table[level][r])
+            }
+            None => M::identity(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monoid::{Gcd, Max, Min, ProductMod, Sum};
+    use crate::SegmentTree;
+
+    #[test]
+    fn test_sparse_table_against_segment_tree() {
+        for n in [1, 2, 3, 7, 8, 9, 31, 64] {
+            let a: Vec<i64> = (0..n as i64).map(|i| (i * 37 + 11) % 23 - 10).collect();
+            let gcd_values: Vec<u64> = (0..n as u64).map(|i| (i % 5 + 1) * 6).collect();
+
+            let min_table = SparseTable::<Min>::init(&a);
+            let max_table = SparseTable::<Max>::init(&a);
+            let gcd_table = SparseTable::<Gcd>::init(&gcd_values);
+            let sum_table = DisjointSparseTable::<Sum>::init(&a);
+
+            let min_tree = SegmentTree::<Min>::init(&a);
+            let max_tree = SegmentTree::<Max>::init(&a);
+            let gcd_tree = SegmentTree::<Gcd>::init(&gcd_values);
+            let sum_tree = SegmentTree::<Sum>::init(&a);
+
+            for l in 0..=n {
+                for r in l..=n {
+                    assert_eq!(min_table.query(l..r), min_tree.query(l..r));
+                    assert_eq!(max_table.query(l..r), max_tree.query(l..r));
+                    assert_eq!(gcd_table.query(l..r), gcd_tree.query(l..r));
+                    assert_eq!(sum_table.query(l..r), sum_tree.query(l..r));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse_tables_edge_cases() {
+        // a product cannot be computed from overlapping blocks, the disjoint table handles it
+        let product_table = DisjointSparseTable::<ProductMod<1_000_000_007>>::init(&[2, 3, 5, 7]);
+        assert_eq!(product_table.query(..), Ok(210));
+        assert_eq!(product_table.query(1..3), Ok(15));
+        assert_eq!(product_table.query(2..=2), Ok(5));
+
+        let empty = SparseTable::<Max>::init(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.query(..), Ok(i64::MIN));
+
+        let table = SparseTable::<Min>::init(&[4, 2, 6]);
+        assert_eq!(
+            table.query(1..4),
+            Err(RangeError::OutOfBounds { end: 4, len: 3 })
+        );
+        let sum_table = DisjointSparseTable::<Sum>::init(&[4, 2, 6]);
+        assert_eq!(
+            sum_table.query(2..=3),
+            Err(RangeError::OutOfBounds { end: 4, len: 3 })
+        );
+    }
+}
@@ -0,0 +1,211 @@
+use crate::dynamic::CoordinateCompression;
+use crate::lazy::Add;
+use crate::monoid::MinCount;
+use crate::range::RangeError;
+use crate::SegmentTree;
+
+/// Axis-aligned rectangle `[x1, x2) x [y1, y2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x1: u64,
+    pub y1: u64,
+    pub x2: u64,
+    pub y2: u64,
+}
+
+impl Rect {
+    pub fn new(x1: u64, y1: u64, x2: u64, y2: u64) -> Self {
+        Self { x1, y1, x2, y2 }
+    }
+}
+
+/// Number of positions of `0..n` covered by at least one of the closed `segments` (0-based),
+/// on the dense coverage tree of Problem 2. Fails if a segment does not fit in `0..n`.
+pub fn covered_points(n: usize, segments: &[(usize, usize)]) -> Result<usize, RangeError> {
+    let tree = SegmentTree::coverage_counts_from_segments(n, segments)?;
+    Ok(n - tree.count_zero(..)?)
+}
+
+/// Total length of the union of the half-open `intervals` `[start, end)`, with arbitrary coordinates.
+/// Closed integer segments `[l, r]` are the intervals `[l, r + 1)`.
+pub fn union_length(intervals: &[(u64, u64)]) -> u64 {
+    let intervals: Vec<(u64, u64)> = intervals
+        .iter()
+        .copied()
+        .filter(|&(start, end)| start < end)
+        .collect();
+
+    let compression = CoordinateCompression::new(intervals.iter().flat_map(|&(l, r)| [l, r]));
+    let mut coverage = WeightedCoverage::new(&compression);
+    for &(start, end) in &intervals {
+        coverage.add(&compression, start, end, 1);
+    }
+    coverage.covered_length()
+}
+
+/// Total area of the union of the rectangles (Klee's measure problem in 2D), in O(n log n).
+///
+/// A vertical line sweeps the plane from left to right: each rectangle adds 1 to the coverage of
+/// its `[y1, y2)` when the line reaches `x1`, and removes it at `x2`. Between two events, the area
+/// gained is the covered length of the line times the distance travelled.
+pub fn union_area(rects: &[Rect]) -> u128 {
+    let rects: Vec<&Rect> = rects
+        .iter()
+        .filter(|rect| rect.x1 < rect.x2 && rect.y1 < rect.y2)
+        .collect();
+
+    let compression = CoordinateCompression::new(rects.iter().flat_map(|rect| [rect.y1, rect.y2]));
+    let mut coverage = WeightedCoverage::new(&compression);
+
+    let mut events: Vec<(u64, i32, u64, u64)> = rects
+        .iter()
+        .flat_map(|rect| {
+            [
+                (rect.x1, 1, rect.y1, rect.y2),
+                (rect.x2, -1, rect.y1, rect.y2),
+            ]
+        })
+        .collect();
+    events.sort_unstable();
+
+    let mut area = 0;
+    let mut last_x = events.first().map_or(0, |event| event.0);
+    for (x, delta, y1, y2) in events {
+        area += coverage.covered_length() as u128 * (x - last_x) as u128;
+        coverage.add(&compression, y1, y2, delta);
+        last_x = x;
+    }
+    area
+}
+
+// Coverage tree over the elementary intervals of a compression: each position weighs the length of
+// its interval, so the count of a (min, count) node is the length held at the minimum coverage.
+struct WeightedCoverage {
+    tree: Option<SegmentTree<MinCount<i32>, Add<i32>>>, // None when there is no interval at all
+    total: u64,
+}
+
+impl WeightedCoverage {
+    fn new(compression: &CoordinateCompression) -> Self {
+        let weights: Vec<(i32, usize)> = (0..compression.interval_count())
+            .map(|i| (0, compression.interval_len(i) as usize))
+            .collect();
+
+        Self {
+            total: weights.iter().map(|&(_, len)| len as u64).sum(),
+            tree: (!weights.is_empty()).then(|| SegmentTree::init(&weights)),
+        }
+    }
+
+    // coverage of [start, end) += delta, both ends being points of the compression
+    fn add(&mut self, compression: &CoordinateCompression, start: u64, end: u64, delta: i32) {
+        let range = compression.index(start).unwrap()..compression.index(end).unwrap();
+        if let Some(tree) = &mut self.tree {
+            tree.apply_range(range, delta).unwrap();
+        }
+    }
+
+    // length covered at least once: everything but the length left at coverage 0
+    fn covered_length(&self) -> u64 {
+        let Some(tree) = &self.tree else {
+            return 0;
+        };
+        let (min, len_at_min) = tree.query(..).unwrap();
+        if min == 0 {
+            self.total - len_at_min as u64
+        } else {
+            self.total
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    #[test]
+    fn test_union_length() {
+        assert_eq!(union_length(&[]), 0);
+        assert_eq!(union_length(&[(5, 5)]), 0);
+        assert_eq!(covered_points(0, &[]), Ok(0));
+        assert_eq!(
+            covered_points(4, &[(1, 4)]),
+            Err(RangeError::OutOfBounds { end: 5, len: 4 })
+        );
+        assert_eq!(union_length(&[(0, 10), (5, 15), (20, 25)]), 20);
+        assert_eq!(
+            union_length(&[(0, 10u64.pow(18)), (10u64.pow(17), 2 * 10u64.pow(18))]),
+            2 * 10u64.pow(18)
+        );
+
+        // closed integer segments, against the dense coverage tree and a brute force
+        let mut rng = Lcg(4);
+        for _ in 0..50 {
+            let n = 1 + rng.next(40);
+            let segments: Vec<(usize, usize)> = (0..rng.next(6))
+                .map(|_| {
+                    let (a, b) = (rng.next(n), rng.next(n));
+                    (a.min(b), a.max(b))
+                })
+                .collect();
+
+            let expected = (0..n)
+                .filter(|&x| segments.iter().any(|&(l, r)| l <= x && x <= r))
+                .count();
+            let intervals: Vec<(u64, u64)> = segments
+                .iter()
+                .map(|&(l, r)| (l as u64, r as u64 + 1))
+                .collect();
+
+            assert_eq!(covered_points(n, &segments), Ok(expected));
+            assert_eq!(union_length(&intervals), expected as u64);
+        }
+    }
+
+    #[test]
+    fn test_union_area() {
+        assert_eq!(union_area(&[]), 0);
+        assert_eq!(
+            union_area(&[Rect::new(0, 0, 2, 2), Rect::new(1, 1, 3, 3)]),
+            7
+        );
+        // one rectangle inside the other, and a degenerate one
+        assert_eq!(
+            union_area(&[
+                Rect::new(0, 0, 10, 10),
+                Rect::new(2, 2, 5, 5),
+                Rect::new(4, 0, 4, 20)
+            ]),
+            100
+        );
+        // coordinates large enough to overflow a u64 area
+        let big = 1u64 << 40;
+        assert_eq!(
+            union_area(&[Rect::new(0, 0, big, big), Rect::new(big, 0, 2 * big, big)]),
+            2 * (big as u128) * (big as u128)
+        );
+
+        // against a brute force on a small grid
+        let mut rng = Lcg(11);
+        for _ in 0..50 {
+            let rects: Vec<Rect> = (0..rng.next(6))
+                .map(|_| {
+                    let (a, b) = (rng.next(12) as u64, rng.next(12) as u64);
+                    let (c, d) = (rng.next(12) as u64, rng.next(12) as u64);
+                    Rect::new(a.min(b), c.min(d), a.max(b), c.max(d))
+                })
+                .collect();
+
+            let expected = (0..12u64)
+                .flat_map(|x| (0..12u64).map(move |y| (x, y)))
+                .filter(|&(x, y)| {
+                    rects
+                        .iter()
+                        .any(|rect| rect.x1 <= x && x < rect.x2 && rect.y1 <= y && y < rect.y2)
+                })
+                .count();
+            assert_eq!(union_area(&rects), expected as u128);
+        }
+    }
+}
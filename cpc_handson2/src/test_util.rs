@@ -0,0 +1,13 @@
+/// Small deterministic generator for the randomized tests.
+pub struct Lcg(pub u64);
+
+impl Lcg {
+    /// Next value, uniform enough in `0..bound`.
+    pub fn next(&mut self, bound: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % bound
+    }
+}
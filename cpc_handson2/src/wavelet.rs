@@ -0,0 +1,366 @@
+use std::ops::RangeBounds;
+
+use crate::range::{to_half_open, RangeError};
+
+/// Wavelet matrix over a static array: counts, sums and order statistics on the values of a subarray,
+/// each in O(log σ) where σ is the number of distinct values.
+///
+/// The values are replaced by their rank among the distinct values. Level `h` looks at one bit of the
+/// ranks, from the highest one down, and stably moves the positions whose bit is 0 before the ones
+/// whose bit is 1; a range of positions is followed from level to level by counting the zeros before
+/// its two ends.
+pub struct WaveletMatrix {
+    n: usize,
+    sorted: Vec<i64>,  // distinct values, the rank of a value is its position here
+    zeros: Vec<usize>, // number of positions whose bit is 0, per level
+    zeros_before: Vec<Vec<usize>>, // zeros_before[h][i]: positions before i whose bit is 0 at level h
+    sums: Vec<Vec<i64>>,           // sums[h]: prefix sums of the values in the order after level h
+    prefix_sum: Vec<i64>,          // prefix sums of the array itself
+}
+
+impl WaveletMatrix {
+    pub fn new(a: &[i64]) -> Self {
+        let mut sorted = a.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        let max_rank = sorted.len().saturating_sub(1);
+        let bits = (usize::BITS - max_rank.leading_zeros()).max(1);
+
+        let mut ranks: Vec<usize> = a
+            .iter()
+            .map(|value| sorted.binary_search(value).unwrap())
+            .collect();
+
+        let mut zeros = Vec::new();
+        let mut zeros_before = Vec::new();
+        let mut sums = Vec::new();
+        for h in (0..bits).rev() {
+            let mut before = Vec::with_capacity(a.len() + 1);
+            before.push(0);
+            for &rank in &ranks {
+                before.push(before.last().unwrap() + ((rank >> h) & 1 == 0) as usize);
+            }
+
+            // stable partition on the bit h
+            let (low, high): (Vec<usize>, Vec<usize>) =
+                ranks.iter().partition(|&&rank| (rank >> h) & 1 == 0);
+            zeros.push(low.len());
+            ranks = low;
+            ranks.extend(high);
+
+            sums.push(prefix_sums(ranks.iter().map(|&rank| sorted[rank])));
+            zeros_before.push(before);
+        }
+
+        Self {
+            n: a.len(),
+            sorted,
+            zeros,
+            zeros_before,
+            sums,
+            prefix_sum: prefix_sums(a.iter().copied()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// How many values of `a[range]` are strictly less than `x`.
+    pub fn count_less(&self, range: impl RangeBounds<usize>, x: i64) -> Result<usize, RangeError> {
+        let bound = self.sorted.partition_point(|&value| value < x);
+        Ok(self.below_rank(range, bound)?.0)
+    }
+
+    /// How many values of `a[range]` are at most `x`.
+    pub fn count_at_most(
+        &self,
+        range: impl RangeBounds<usize>,
+        x: i64,
+    ) -> Result<usize, RangeError> {
+        let bound = self.sorted.partition_point(|&value| value <= x);
+        Ok(self.below_rank(range, bound)?.0)
+    }
+
+    /// Sum of the values of `a[range]` that are strictly less than `x`.
+    pub fn sum_less(&self, range: impl RangeBounds<usize>, x: i64) -> Result<i64, RangeError> {
+        let bound = self.sorted.partition_point(|&value| value < x);
+        Ok(self.below_rank(range, bound)?.1)
+    }
+
+    /// The `k`-th smallest value of `a[range]`, counting from 1 and with repetitions,
+    /// or `None` if `k` is 0 or larger than the length of the range.
+    pub fn kth_smallest(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: usize,
+    ) -> Result<Option<i64>, RangeError> {
+        let (mut l, mut r) = to_half_open(range, self.n)?;
+        if k == 0 || k > r - l {
+            return Ok(None);
+        }
+
+        let mut remaining = k;
+        let mut rank = 0;
+        for (level, before) in self.zeros_before.iter().enumerate() {
+            let zeros_in_range = before[r] - before[l];
+            rank <<= 1;
+
+            if remaining <= zeros_in_range {
+                (l, r) = (before[l], before[r]);
+            } else {
+                remaining -= zeros_in_range;
+                rank |= 1;
+                (l, r) = self.to_ones(level, l, r);
+            }
+        }
+
+        Ok(Some(self.sorted[rank]))
+    }
+
+    // (count, sum) of the values of `a[range]` whose rank is below `bound`
+    fn below_rank(
+        &self,
+        range: impl RangeBounds<usize>,
+        bound: usize,
+    ) -> Result<(usize, i64), RangeError> {
+        let (mut l, mut r) = to_half_open(range, self.n)?;
+        if bound >= self.sorted.len() {
+            return Ok((r - l, self.prefix_sum[r] - self.prefix_sum[l]));
+        }
+
+        let levels = self.zeros_before.len();
+        let (mut count, mut sum) = (0, 0);
+        for (level, before) in self.zeros_before.iter().enumerate() {
+            let (zeros_l, zeros_r) = (before[l], before[r]);
+
+            if (bound >> (levels - 1 - level)) & 1 == 1 {
+                // the positions with a 0 here are below the bound, and the search goes on with the 1s
+                count += zeros_r - zeros_l;
+                sum += self.sums[level][zeros_r] - self.sums[level][zeros_l];
+                (l, r) = self.to_ones(level, l, r);
+            } else {
+                (l, r) = (zeros_l, zeros_r);
+            }
+        }
+
+        Ok((count, sum))
+    }
+
+    // positions of the range [l, r) among the 1s, once moved after the 0s of the level
+    fn to_ones(&self, level: usize, l: usize, r: usize) -> (usize, usize) {
+        let before = &self.zeros_before[level];
+        let zeros = self.zeros[level];
+        (zeros + l - before[l], zeros + r - before[r])
+    }
+}
+
+/// Merge-sort tree: a segment tree whose nodes keep the sorted values of their range, with prefix sums.
+/// Simpler than `WaveletMatrix` but slower (O(log² n) per count, O(log³ n) for the k-th smallest),
+/// useful as a baseline to cross-check it.
+pub struct MergeSortTree {
+    n: usize,
+    size: usize,
+    sorted: Vec<Vec<i64>>, // bottom-up layout: node 1 is the root, the leaves are size..size + n
+    sums: Vec<Vec<i64>>,
+}
+
+impl MergeSortTree {
+    pub fn new(a: &[i64]) -> Self {
+        let size = a.len().next_power_of_two();
+        let mut sorted = vec![Vec::new(); 2 * size];
+        for (i, &value) in a.iter().enumerate() {
+            sorted[size + i] = vec![value];
+        }
+        for k in (1..size).rev() {
+            sorted[k] = merge(&sorted[2 * k], &sorted[2 * k + 1]);
+        }
+
+        let sums = sorted
+            .iter()
+            .map(|values| prefix_sums(values.iter().copied()))
+            .collect();
+
+        Self {
+            n: a.len(),
+            size,
+            sorted,
+            sums,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// How many values of `a[range]` are strictly less than `x`.
+    pub fn count_less(&self, range: impl RangeBounds<usize>, x: i64) -> Result<usize, RangeError> {
+        Ok(self.below(range, |value| value < x)?.0)
+    }
+
+    /// How many values of `a[range]` are at most `x`.
+    pub fn count_at_most(
+        &self,
+        range: impl RangeBounds<usize>,
+        x: i64,
+    ) -> Result<usize, RangeError> {
+        Ok(self.below(range, |value| value <= x)?.0)
+    }
+
+    /// Sum of the values of `a[range]` that are strictly less than `x`.
+    pub fn sum_less(&self, range: impl RangeBounds<usize>, x: i64) -> Result<i64, RangeError> {
+        Ok(self.below(range, |value| value < x)?.1)
+    }
+
+    /// The `k`-th smallest value of `a[range]`, counting from 1 and with repetitions,
+    /// or `None` if `k` is 0 or larger than the length of the range.
+    pub fn kth_smallest(
+        &self,
+        range: impl RangeBounds<usize>,
+        k: usize,
+    ) -> Result<Option<i64>, RangeError> {
+        let (l, r) = to_half_open(range, self.n)?;
+        if k == 0 || k > r - l {
+            return Ok(None);
+        }
+
+        // smallest value of the array with at least k values of the range at most it
+        let all_values = &self.sorted[1];
+        let position =
+            all_values.partition_point(|&x| self.below(l..r, |value| value <= x).unwrap().0 < k);
+        Ok(Some(all_values[position]))
+    }
+
+    // (count, sum) of the values of `a[range]` for which `is_below` holds, `is_below` being true
+    // on a prefix of the sorted values
+    fn below<F>(
+        &self,
+        range: impl RangeBounds<usize>,
+        is_below: F,
+    ) -> Result<(usize, i64), RangeError>
+    where
+        F: Fn(i64) -> bool,
+    {
+        let (l, r) = to_half_open(range, self.n)?;
+        let (mut l, mut r) = (l + self.size, r + self.size);
+
+        let (mut count, mut sum) = (0, 0);
+        let mut take = |node: usize| {
+            let below = self.sorted[node].partition_point(|&value| is_below(value));
+            count += below;
+            sum += self.sums[node][below];
+        };
+
+        while l < r {
+            if l & 1 == 1 {
+                take(l);
+                l += 1;
+            }
+            if r & 1 == 1 {
+                r -= 1;
+                take(r);
+            }
+            l >>= 1;
+            r >>= 1;
+        }
+
+        Ok((count, sum))
+    }
+}
+
+fn prefix_sums(values: impl Iterator<Item = i64>) -> Vec<i64> {
+    let mut sums = vec![0];
+    for value in values {
+        sums.push(sums.last().unwrap() + value);
+    }
+    sums
+}
+
+fn merge(a: &[i64], b: &[i64]) -> Vec<i64> {
+    let mut merged = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a[i] <= b[j] {
+            merged.push(a[i]);
+            i += 1;
+        } else {
+            merged.push(b[j]);
+            j += 1;
+        }
+    }
+    merged.extend_from_slice(&a[i..]);
+    merged.extend_from_slice(&b[j..]);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::Lcg;
+
+    #[test]
+    fn test_wavelet_against_merge_sort_tree() {
+        for (n, spread) in [(1, 3), (2, 1), (17, 5), (40, 100), (64, 1000)] {
+            let mut rng = Lcg(n as u64);
+            let a: Vec<i64> = (0..n)
+                .map(|_| rng.next(2 * spread) as i64 - spread as i64)
+                .collect();
+            let wavelet = WaveletMatrix::new(&a);
+            let merge_sort = MergeSortTree::new(&a);
+
+            for _ in 0..300 {
+                let (x, y) = (rng.next(n + 1), rng.next(n + 1));
+                let range = x.min(y)..x.max(y);
+                let value = rng.next(2 * spread + 2) as i64 - spread as i64 - 1;
+                let k = rng.next(range.len() + 2);
+
+                let slice = &a[range.clone()];
+                let less = slice.iter().filter(|&&v| v < value).count();
+                let at_most = slice.iter().filter(|&&v| v <= value).count();
+                let sum: i64 = slice.iter().filter(|&&v| v < value).sum();
+                let mut sorted = slice.to_vec();
+                sorted.sort();
+                let kth = k.checked_sub(1).and_then(|i| sorted.get(i)).copied();
+
+                assert_eq!(wavelet.count_less(range.clone(), value), Ok(less));
+                assert_eq!(merge_sort.count_less(range.clone(), value), Ok(less));
+                assert_eq!(wavelet.count_at_most(range.clone(), value), Ok(at_most));
+                assert_eq!(merge_sort.count_at_most(range.clone(), value), Ok(at_most));
+                assert_eq!(wavelet.sum_less(range.clone(), value), Ok(sum));
+                assert_eq!(merge_sort.sum_less(range.clone(), value), Ok(sum));
+                assert_eq!(wavelet.kth_smallest(range.clone(), k), Ok(kth));
+                assert_eq!(merge_sort.kth_smallest(range, k), Ok(kth));
+            }
+        }
+    }
+
+    #[test]
+    fn test_wavelet_edge_cases() {
+        let wavelet = WaveletMatrix::new(&[]);
+        assert!(wavelet.is_empty());
+        assert_eq!(wavelet.count_less(.., 5), Ok(0));
+        assert_eq!(wavelet.kth_smallest(.., 1), Ok(None));
+
+        let wavelet = WaveletMatrix::new(&[7, 7, 7]);
+        assert_eq!(wavelet.count_at_most(.., 7), Ok(3));
+        assert_eq!(wavelet.sum_less(1.., 8), Ok(14));
+        assert_eq!(wavelet.kth_smallest(..2, 2), Ok(Some(7)));
+        assert_eq!(
+            wavelet.count_less(2..4, 0),
+            Err(RangeError::OutOfBounds { end: 4, len: 3 })
+        );
+        assert_eq!(
+            MergeSortTree::new(&[7, 7, 7]).sum_less(..=3, 0),
+            Err(RangeError::OutOfBounds { end: 4, len: 3 })
+        );
+    }
+}